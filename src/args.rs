@@ -1,16 +1,113 @@
 use clap::{Args, Parser, Subcommand};
 
+use crate::output::OutputFormat;
+use crate::pixela::Profile;
+use crate::user_data::{Cadence, GoalPeriod};
+
 #[derive(Debug)]
 // enum storing all possible argument types for cleaner data passing
 pub enum ParsedArguments<'a> {
     PixelArgs(PixelArgs<'a>),
+    BatchArgs(BatchArgs<'a>),
     LoginArgs(LoginArgs<'a>),
     SumGraphArgs(SumGraphArgs),
+    SumGraphCheck,
     SumArgs(SumArgs<'a>),
     NewUserData(NewUserArgs<'a>),
     GraphCreateArgs(CreateGraphArgs<'a>),
     StreakGetArgs(StreakGetArgs<'a>),
     RemoveArgs(RemoveArgs<'a>),
+    GoalArgs(GoalArgs<'a>),
+    CadenceArgs(CadenceArgs<'a>),
+    RestArgs(RestArgs<'a>),
+    AliasArgs(AliasArgs<'a>),
+    ConfigArgs(ConfigArgs<'a>),
+    TimerArgs(TimerArgs<'a>),
+    AccountArgs(AccountArgs<'a>),
+}
+
+#[derive(Debug)]
+pub enum AccountArgs<'a> {
+    Show,
+    Set(Profile),
+    Token {
+        new_token: &'a str,
+    },
+    Delete,
+}
+
+#[derive(Debug)]
+pub enum TimerArgs<'a> {
+    Start {
+        graph: &'a str,
+    },
+    Stop,
+    Cancel,
+    Status,
+}
+
+#[derive(Debug)]
+pub enum ConfigArgs<'a> {
+    List,
+    Get {
+        key: &'a str,
+    },
+    Set {
+        key: &'a str,
+        value: &'a str,
+    },
+    Edit,
+}
+
+#[derive(Debug)]
+pub enum AliasArgs<'a> {
+    List,
+    Add {
+        alias: &'a str,
+        graph: &'a str,
+    },
+    Remove {
+        alias: &'a str,
+    },
+}
+
+#[derive(Debug)]
+pub enum RestArgs<'a> {
+    List,
+    // inclusive range of days, to is the same as from for a single day
+    Add {
+        from: &'a str,
+        to: &'a str,
+    },
+    Remove {
+        from: &'a str,
+        to: &'a str,
+    },
+}
+
+#[derive(Debug)]
+pub enum CadenceArgs<'a> {
+    List,
+    Set {
+        graph: &'a str,
+        cadence: Cadence,
+    },
+    Remove {
+        graph: &'a str,
+    },
+}
+
+#[derive(Debug)]
+pub enum GoalArgs<'a> {
+    Show,
+    Set {
+        graph: &'a str,
+        target: f64,
+        period: GoalPeriod,
+    },
+    Remove {
+        graph: &'a str,
+    },
 }
 
 #[derive(Debug)]
@@ -19,40 +116,62 @@ pub struct RemoveArgs<'a> {
 }
 #[derive(Debug)]
 pub struct StreakGetArgs<'a> {
-    pub graph_id: &'a str
+    pub graph_id: &'a str,
+    // count only days where the graph's goal was met
+    pub goal: bool,
 }
 
 #[derive(Debug)]
 pub struct CreateGraphArgs<'a> {
     pub id: &'a str,
     pub name: &'a str ,
-    pub number_type: &'a str,
+    pub number_type: Option<&'a str>,
     pub unit: &'a str,
-    pub color: &'a str,
+    pub color: Option<&'a str>,
 }
 #[derive(Debug)]
 pub struct NewUserArgs<'a> {
-    pub token: &'a str,
+    // asked for when not passed
+    pub token: Option<&'a str>,
     pub username: &'a str,
-    pub minor: bool,
-    pub tos: bool,
+    // false means the user is asked
+    pub not_minor: bool,
+    pub agree_tos: bool,
 }
 #[derive(Debug)]
 pub struct PixelArgs<'a> {
     pub graph: &'a str,
     pub date: Option<&'a str>,
     pub quantity: &'a str,
+    // put in the pixel's optional data
+    pub note: Option<&'a str>,
+    pub data: Option<&'a str>,
+}
+#[derive(Debug)]
+pub struct BatchArgs<'a> {
+    pub date: Option<&'a str>,
+    // "graph=quantity" pairs, parsed by the worker
+    pub pixels: Vec<&'a str>,
+    // the same optional data goes to every pixel
+    pub note: Option<&'a str>,
+    pub data: Option<&'a str>,
 }
 #[derive(Debug)]
 pub struct SumArgs<'a> {
     pub date: Option<&'a str>,
     pub name: Option<&'a str>,
+    // inclusive date range to recompute, both ends have to be present
+    pub from: Option<&'a str>,
+    pub to: Option<&'a str>,
+    pub jobs: usize,
 }
 #[derive(Debug)]
 pub struct SumGraphArgs {
     // graphs that are suppossed to get summed up and sum_graph that stores id of a graph the
     // result is uploaded to
     pub sum_graph_amount: usize,
+    // recompute dependent sum graphs after every send
+    pub auto_sum: bool,
 }
 
 #[derive(Debug)]
@@ -60,6 +179,8 @@ pub struct LoginArgs<'a> {
     //username and user's api key
     pub name: &'a str,
     pub api_key: &'a str,
+    // save without asking pixela first
+    pub no_verify: bool,
 }
 
 #[derive(Debug, Parser)]
@@ -67,6 +188,18 @@ pub struct LoginArgs<'a> {
 pub struct HabitCLIArgs {
     #[clap(subcommand)]
     pub command_type: CommandType,
+    /// How results are printed, output of config.toml (plain by default) when left out.
+    #[arg(short, long, global = true, value_enum)]
+    pub output: Option<OutputFormat>,
+    /// Seconds after which a request to Pixela is abandoned.
+    #[arg(long, global = true, default_value_t = 30)]
+    pub timeout: u64,
+    /// Fetch graphs and pixels from Pixela instead of the local cache.
+    #[arg(long, global = true)]
+    pub refresh: bool,
+    /// Keeps config files and the database in this directory (overrides HABITCLI_HOME).
+    #[arg(long, global = true, value_name = "DIR")]
+    pub config_dir: Option<std::path::PathBuf>,
 }
 // all possible commands
 #[derive(Debug, Subcommand)]
@@ -75,6 +208,14 @@ pub enum CommandType {
     Signup(NewUser),
     /// Use to log in with your pixela api token and pixela name.
     Login(LoginUser),
+    /// Removes your credentials and cached data from this device.
+    Logout(Logout),
+    /// Deletes all local habitCLI data: credentials, config files, caches and the database.
+    Reset(Reset),
+    /// Shows your public profile, or changes it, your token or deletes your account.
+    Account(AccountCommand),
+    /// Shows who you are logged in as and checks the credentials with Pixela.
+    Whoami(Whoami),
     /// Print your data.
     Data(GetData),
     /// Creates a new graph on Pixela.
@@ -83,8 +224,14 @@ pub enum CommandType {
     Remove(RemoveGraph),
     /// Use to send pixels to Pixela.
     Send(SendPixel),
+    /// Sends "graph=quantity" lines read from stdin.
+    Log(LogPixels),
+    /// Asks for today's quantity of every graph without a pixel yet.
+    Checkin(Checkin),
     /// Use to get pixels data from Pixela.
     Get(GetPixel),
+    /// Lists the notes saved with pixels of a graph.
+    Notes(GetNotes),
     /// List all graphs.
     List(GetList),
     /// Calculates your current streak of consecutive pixels
@@ -93,43 +240,319 @@ pub enum CommandType {
     SetupSum(SumGraph),
     /// Sums all progress of your graphs.
     Sum(SumGraphs),
+    /// Shows progress towards your goals, or sets them.
+    Goals(GoalsCommand),
+    /// Lists how often your habits should be done, or sets it for a graph.
+    Cadence(CadenceCommand),
+    /// Manages rest days that don't break your streaks.
+    Rest(RestCommand),
+    /// Manages local names for your graph ids.
+    Alias(AliasCommand),
+    /// Shows or changes the settings in config.toml.
+    Config(ConfigCommand),
+    /// Times an activity and adds the elapsed time to today's pixel.
+    Timer(TimerCommand),
+    /// Runs in the background and reminds you about graphs without a pixel for today.
+    Daemon(Daemon),
+    /// Opens an interactive dashboard of all your graphs.
+    Tui(Tui),
+    /// Prints a completion script for your shell.
+    Completions(Completions),
+    /// Prints graph ids for shell completion.
+    #[command(hide = true)]
+    CompleteGraphs(CompleteGraphs),
     
 }
 
 #[derive(Debug, Args)]
 pub struct NewUser {
-    /// Your new password.
-    password: String,
-    /// Your new username.
+    /// Your new username, a lowercase letter followed by 1 to 32 lowercase letters, digits or hyphens.
     username: String,
-    /// Do you agree to Pixela's TOS? [yes/no]
-    tos: String,
-    /// Are you a minor? [yes/no]
-    minor: String
+    /// Your new token (password), 8 to 128 printable ascii characters. Asked for when left out.
+    token: Option<String>,
+    /// Agree to Pixela's terms of service without being asked.
+    #[arg(long)]
+    agree_tos: bool,
+    /// Confirm you are not a minor, or have your guardian's consent, without being asked.
+    #[arg(long)]
+    not_minor: bool,
 }
 
 #[derive(Debug, Args)]
 pub struct GetList {}
 
+#[derive(Debug, Args)]
+pub struct Tui {}
+
+#[derive(Debug, Args)]
+pub struct Whoami {}
+
+#[derive(Debug, Args)]
+pub struct Logout {
+    /// Don't ask for confirmation.
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct Reset {
+    /// Don't ask for confirmation.
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
 #[derive(Debug, Args)]
 pub struct RemoveGraph {
-    /// Graph name.
+    /// Graph id or alias.
     pub graph_name: String
 }
 #[derive(Debug, Args)]
 pub struct GetStreak {
-    /// Graph id.
-    graph_id: String
+    /// Graph id or alias.
+    graph_id: String,
+    /// Count only days where the daily goal of the graph was met.
+    #[arg(short, long)]
+    goal: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct Completions {
+    /// Shell to generate the script for.
+    #[arg(value_enum)]
+    pub shell: clap_complete::Shell,
+}
+
+#[derive(Debug, Args)]
+pub struct CompleteGraphs {
+    /// Print names of sum graphs instead.
+    #[arg(long)]
+    pub sum: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct Daemon {
+    /// Check your graphs once right now instead of waiting for reminder times.
+    #[arg(long)]
+    pub once: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct RestCommand {
+    #[command(subcommand)]
+    command: RestSubcommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum RestSubcommand {
+    /// Excuses a day (or every day up to --to) from your streaks.
+    Add {
+        /// Rest day. Format: "yyyymmdd".
+        date: String,
+        /// Last rest day of a vacation. Format: "yyyymmdd".
+        #[arg(short, long)]
+        to: Option<String>,
+    },
+    /// Removes a rest day (or every rest day up to --to).
+    Remove {
+        /// Rest day. Format: "yyyymmdd".
+        date: String,
+        /// Last rest day to remove. Format: "yyyymmdd".
+        #[arg(short, long)]
+        to: Option<String>,
+    },
+    /// Lists all of your rest days.
+    List,
+}
+
+#[derive(Debug, Args)]
+pub struct AliasCommand {
+    #[command(subcommand)]
+    command: AliasSubcommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AliasSubcommand {
+    /// Lets you use a name of your choice in place of a graph id.
+    Add {
+        /// New name for the graph.
+        alias: String,
+        /// Graph id.
+        graph_id: String,
+    },
+    /// Removes an alias.
+    Remove {
+        /// Alias to remove.
+        alias: String,
+    },
+    /// Lists all of your aliases.
+    List,
+}
+
+#[derive(Debug, Args)]
+pub struct ConfigCommand {
+    #[command(subcommand)]
+    command: ConfigSubcommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigSubcommand {
+    /// Lists every setting with its current value.
+    List,
+    /// Prints a setting, ex. "retry.attempts", or a whole section like "aliases".
+    Get {
+        /// Name of the setting.
+        key: String,
+    },
+    /// Changes a setting, ex. "config set timezone Europe/Warsaw".
+    Set {
+        /// Name of the setting.
+        key: String,
+        /// New value, numbers, booleans and arrays are read as toml.
+        value: String,
+    },
+    /// Opens config.toml in $VISUAL or $EDITOR.
+    Edit,
+}
+
+#[derive(Debug, Args)]
+pub struct AccountCommand {
+    #[command(subcommand)]
+    command: Option<AccountSubcommand>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AccountSubcommand {
+    /// Updates fields of your public profile, fields that aren't passed stay as they are.
+    #[command(group(clap::ArgGroup::new("fields").required(true).multiple(true)))]
+    Set {
+        /// Name shown on your profile.
+        #[arg(long, group = "fields")]
+        display_name: Option<String>,
+        /// Title shown under your name.
+        #[arg(long, group = "fields")]
+        title: Option<String>,
+        /// Timezone of your profile, ex. "Europe/Warsaw".
+        #[arg(long, group = "fields")]
+        timezone: Option<String>,
+        /// Link to a page about you.
+        #[arg(long, group = "fields")]
+        about_url: Option<String>,
+        /// Email of the gravatar used as your icon.
+        #[arg(long, group = "fields")]
+        gravatar_email: Option<String>,
+        /// Links to things you contribute to, repeat for several.
+        #[arg(long, group = "fields")]
+        contribute_url: Vec<String>,
+        /// Graph id (or alias) pinned to your profile.
+        #[arg(long, group = "fields")]
+        pinned_graph: Option<String>,
+    },
+    /// Replaces your Pixela token, this device keeps working with the new one.
+    Token {
+        /// New token, 8 to 128 printable ascii characters.
+        new_token: String,
+    },
+    /// Deletes your Pixela account with all of its graphs, asks for your username to confirm.
+    Delete,
+}
+
+#[derive(Debug, Args)]
+pub struct TimerCommand {
+    #[command(subcommand)]
+    command: TimerSubcommand,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TimerSubcommand {
+    /// Starts timing a graph, its unit has to be seconds, minutes or hours.
+    Start {
+        /// Graph id or alias.
+        graph_id: String,
+    },
+    /// Stops the timer and adds the elapsed time to today's pixel.
+    Stop,
+    /// Stops the timer without sending anything.
+    Cancel,
+    /// Shows the running timer.
+    Status,
+}
+
+#[derive(Debug, Args)]
+pub struct CadenceCommand {
+    #[command(subcommand)]
+    command: Option<CadenceSubcommand>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CadenceSubcommand {
+    /// Sets how often a graph's habit should be done, streaks are counted in these units.
+    Set {
+        /// Graph id or alias.
+        graph_id: String,
+        /// How often the habit should be done.
+        #[arg(value_enum)]
+        every: CadenceKind,
+        /// Days with pixels needed in every week or month.
+        #[arg(short, long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+        times: u32,
+    },
+    /// Removes the cadence of a graph, making it a daily habit again.
+    Remove {
+        /// Graph id or alias.
+        graph_id: String,
+    },
+}
+
+#[derive(Debug, Clone, clap::ValueEnum)]
+pub enum CadenceKind {
+    Daily,
+    Weekdays,
+    Week,
+    Month,
+}
+
+#[derive(Debug, Args)]
+pub struct GoalsCommand {
+    #[command(subcommand)]
+    command: Option<GoalSubcommand>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum GoalSubcommand {
+    /// Sets a goal for a graph, replacing the previous one.
+    Set {
+        /// Graph id or alias.
+        graph_id: String,
+        /// Quantity that has to be reached.
+        target: f64,
+        /// Period in which the target has to be reached.
+        #[arg(short, long, value_enum, default_value_t = GoalPeriod::Day)]
+        period: GoalPeriod,
+    },
+    /// Removes the goal of a graph.
+    Remove {
+        /// Graph id or alias.
+        graph_id: String,
+    },
 }
 #[derive(Debug, Args)]
 pub struct SumGraphs {
     /// Optional date to sum from. By default today.
-    #[clap(short,long)]
+    #[clap(short,long, conflicts_with_all = ["from", "to"])]
     date: Option<String> ,
     /// Specific (saved) sum graph that you want updated. When not passed all sum graphs will be
     /// updated.
     #[clap(short,long)]
-    name: Option<String> 
+    name: Option<String>,
+    /// First day of a range to recompute. Format: "yyyymmdd". Requires --to.
+    #[clap(long, requires = "to")]
+    from: Option<String>,
+    /// Last day (inclusive) of a range to recompute. Format: "yyyymmdd". Requires --from.
+    #[clap(long, requires = "from")]
+    to: Option<String>,
+    /// How many days are summed at the same time while recomputing a range.
+    #[clap(short, long, default_value_t = 4)]
+    jobs: usize,
 }
 
 #[derive(Debug, Args)]
@@ -143,21 +566,35 @@ pub struct CreateGraph {
     /// Name of the new graph.
     pub name: String,
 
-    /// Type of the value of the unit (only int and float are supported)
-    pub number_type: String,
-
     /// Unit used to track data on your graph (ex. hours, commits, miles).
     pub unit: String,
 
-    /// Color of graphs pixels. Valid ones are: shibafu (green), momiji (red), sora (blue), ichou (yellow), ajisai (purple) and kuro (black).
-    pub color: String,
+    /// Type of the value of the unit (only int and float are supported), graph.type of config.toml when left out.
+    #[arg(short = 't', long = "type", value_name = "TYPE")]
+    pub number_type: Option<String>,
+
+    /// Color of graphs pixels. Valid ones are: shibafu (green), momiji (red), sora (blue), ichou (yellow), ajisai (purple) and kuro (black), graph.color of config.toml when left out.
+    #[arg(short, long)]
+    pub color: Option<String>,
 }
 #[derive(Debug, Args)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct SumGraph {
+    #[command(subcommand)]
+    command: Option<SumGraphCommand>,
     /// Number of sum graphs you want to add.
-    num_of_sum_graphs: usize
+    #[arg(required = true)]
+    num_of_sum_graphs: Option<usize>,
+    /// Automatically update dependent sum graphs every time you send a pixel.
+    #[clap(short, long)]
+    auto_sum: bool,
+}
+#[derive(Debug, Subcommand)]
+pub enum SumGraphCommand {
+    /// Check saved sum graphs against the graphs on your Pixela account.
+    Check,
 }
-        
+
 #[derive(Debug, Args)]
 pub struct LoginUser {
     /// Pixela username.
@@ -166,16 +603,52 @@ pub struct LoginUser {
     #[arg()]
     /// Pixela api key.
     api_key: String,
+    /// Save the credentials without checking them with Pixela, for example while offline.
+    #[arg(long)]
+    no_verify: bool,
 }
 #[derive(Debug, Args)]
 pub struct SendPixel {
     /// Date of a pixel that you wish to modify. Format: "yyyymmdd". Leave blank to upload for today.
     #[arg(short, long)]
     pub date: Option<String>,
-    /// Graph id to interact with. (the name in the url on pixela)
+    /// Graph id (or alias) and number of commits that you wish to send, or several "graph=quantity" pairs.
+    #[arg(required = true, value_name = "GRAPH QUANTITY | GRAPH=QUANTITY...")]
+    pub pixels: Vec<String>,
+    /// Note saved with the pixel.
+    #[arg(short, long)]
+    pub note: Option<String>,
+    /// Extra json object saved with the pixel, ex. '{"mood": 3}'.
+    #[arg(long)]
+    pub data: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct LogPixels {
+    /// Date of the pixels. Format: "yyyymmdd". Leave blank to upload for today.
+    #[arg(short, long)]
+    pub date: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct Checkin {
+    /// Date to check in for. Format: "yyyymmdd". Leave blank for today.
+    #[arg(short, long)]
+    pub date: Option<String>,
+    /// Graph ids or aliases to ask about, in order. By default the graphs in checkin.toml, or every graph.
+    pub graphs: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct GetNotes {
+    /// Graph id or alias.
     pub graph_id: String,
-    /// Number of commits that you wish to send.
-    pub quantity: String,
+    /// Only show notes containing this text (case insensitive).
+    #[arg(short, long)]
+    pub search: Option<String>,
+    /// Only look at pixels from this day on (yyyymmdd), by default until a year without pixels.
+    #[arg(long)]
+    pub from: Option<String>,
 }
 
 #[derive(Debug, Args)]
@@ -183,7 +656,7 @@ pub struct GetPixel {
     /// Date of a pixel that you wish to modify. Format: "yyyymmdd". Leave blank to upload for today.
     #[arg(short, long)]
     date: Option<String>,
-    /// Graph name to interact with. (the name in the url on pixela)
+    /// Graph id (or alias) to interact with. (the name in the url on pixela)
     graph_id: String,
 }
 
@@ -198,37 +671,137 @@ impl IntoArguments for RemoveGraph {
         return ParsedArguments::RemoveArgs(args)
     }
 }
-impl IntoArguments for GetStreak { fn into_args(&self) -> ParsedArguments { let graph_id = &self.graph_id; let args = StreakGetArgs{graph_id};
+impl IntoArguments for GetStreak { fn into_args(&self) -> ParsedArguments { let graph_id = &self.graph_id; let args = StreakGetArgs{graph_id, goal: self.goal};
         return ParsedArguments::StreakGetArgs(args)
     }
 }
+impl IntoArguments for GoalsCommand {
+    fn into_args(&self) -> ParsedArguments<'_> {
+        let args = match &self.command {
+            None => GoalArgs::Show,
+            Some(GoalSubcommand::Set { graph_id, target, period }) => GoalArgs::Set { graph: graph_id, target: *target, period: period.clone() },
+            Some(GoalSubcommand::Remove { graph_id }) => GoalArgs::Remove { graph: graph_id },
+        };
+        ParsedArguments::GoalArgs(args)
+    }
+}
+impl IntoArguments for RestCommand {
+    fn into_args(&self) -> ParsedArguments<'_> {
+        let args = match &self.command {
+            RestSubcommand::Add { date, to } => RestArgs::Add { from: date, to: to.as_deref().unwrap_or(date) },
+            RestSubcommand::Remove { date, to } => RestArgs::Remove { from: date, to: to.as_deref().unwrap_or(date) },
+            RestSubcommand::List => RestArgs::List,
+        };
+        ParsedArguments::RestArgs(args)
+    }
+}
+impl IntoArguments for AliasCommand {
+    fn into_args(&self) -> ParsedArguments<'_> {
+        let args = match &self.command {
+            AliasSubcommand::Add { alias, graph_id } => AliasArgs::Add { alias, graph: graph_id },
+            AliasSubcommand::Remove { alias } => AliasArgs::Remove { alias },
+            AliasSubcommand::List => AliasArgs::List,
+        };
+        ParsedArguments::AliasArgs(args)
+    }
+}
+impl IntoArguments for ConfigCommand {
+    fn into_args(&self) -> ParsedArguments<'_> {
+        let args = match &self.command {
+            ConfigSubcommand::List => ConfigArgs::List,
+            ConfigSubcommand::Get { key } => ConfigArgs::Get { key },
+            ConfigSubcommand::Set { key, value } => ConfigArgs::Set { key, value },
+            ConfigSubcommand::Edit => ConfigArgs::Edit,
+        };
+        ParsedArguments::ConfigArgs(args)
+    }
+}
+impl IntoArguments for AccountCommand {
+    fn into_args(&self) -> ParsedArguments<'_> {
+        let args = match &self.command {
+            None => AccountArgs::Show,
+            Some(AccountSubcommand::Set { display_name, title, timezone, about_url, gravatar_email, contribute_url, pinned_graph }) => AccountArgs::Set(Profile {
+                display_name: display_name.clone(),
+                gravatar_icon_email: gravatar_email.clone(),
+                title: title.clone(),
+                timezone: timezone.clone(),
+                about_url: about_url.clone(),
+                contribute_urls: (!contribute_url.is_empty()).then(|| contribute_url.clone()),
+                pinned_graph_id: pinned_graph.clone(),
+            }),
+            Some(AccountSubcommand::Token { new_token }) => AccountArgs::Token { new_token },
+            Some(AccountSubcommand::Delete) => AccountArgs::Delete,
+        };
+        ParsedArguments::AccountArgs(args)
+    }
+}
+impl IntoArguments for TimerCommand {
+    fn into_args(&self) -> ParsedArguments<'_> {
+        let args = match &self.command {
+            TimerSubcommand::Start { graph_id } => TimerArgs::Start { graph: graph_id },
+            TimerSubcommand::Stop => TimerArgs::Stop,
+            TimerSubcommand::Cancel => TimerArgs::Cancel,
+            TimerSubcommand::Status => TimerArgs::Status,
+        };
+        ParsedArguments::TimerArgs(args)
+    }
+}
+impl IntoArguments for CadenceCommand {
+    fn into_args(&self) -> ParsedArguments<'_> {
+        let args = match &self.command {
+            None => CadenceArgs::List,
+            Some(CadenceSubcommand::Set { graph_id, every, times }) => {
+                let cadence = match every {
+                    CadenceKind::Daily => Cadence::Daily,
+                    CadenceKind::Weekdays => Cadence::Weekdays,
+                    CadenceKind::Week => Cadence::Week { times: *times },
+                    CadenceKind::Month => Cadence::Month { times: *times },
+                };
+                CadenceArgs::Set { graph: graph_id, cadence }
+            }
+            Some(CadenceSubcommand::Remove { graph_id }) => CadenceArgs::Remove { graph: graph_id },
+        };
+        ParsedArguments::CadenceArgs(args)
+    }
+}
 impl IntoArguments for CreateGraph {
     fn into_args(&self) -> ParsedArguments {
         let CreateGraph{id, name, number_type, unit, color} = &self;
-        let args = CreateGraphArgs{id, name, number_type, unit, color};
+        let args = CreateGraphArgs{id, name, number_type: number_type.as_deref(), unit, color: color.as_deref()};
         return ParsedArguments::GraphCreateArgs(args)
     }
 }
 impl IntoArguments for NewUser {
     fn into_args(&self) -> ParsedArguments {
-        let username = &self.username;
-        let token = &self.username;
-        let minor = if &self.minor == "yes" {true} else {false}; 
-        let tos = if &self.tos == "yes" {true} else {false}; 
-        let args = NewUserArgs{username, token, minor, tos};
-        return ParsedArguments::NewUserData(args)
+        let args = NewUserArgs {
+            username: &self.username,
+            token: self.token.as_deref(),
+            not_minor: self.not_minor,
+            agree_tos: self.agree_tos,
+        };
+        ParsedArguments::NewUserData(args)
     }
 }
 impl IntoArguments for SumGraph {
     fn into_args(&self) -> ParsedArguments {
-        let args = SumGraphArgs {sum_graph_amount: self.num_of_sum_graphs};
+        if let Some(SumGraphCommand::Check) = self.command {
+            return ParsedArguments::SumGraphCheck;
+        }
+        let sum_graph_amount = self.num_of_sum_graphs.expect("Required by clap");
+        let args = SumGraphArgs {sum_graph_amount, auto_sum: self.auto_sum};
         ParsedArguments::SumGraphArgs(args)
     }
 }
 
 impl IntoArguments for SumGraphs {
     fn into_args(&self) -> ParsedArguments {
-        let args = SumArgs{date: self.date.as_deref(), name: self.name.as_deref() };
+        let args = SumArgs{
+            date: self.date.as_deref(),
+            name: self.name.as_deref(),
+            from: self.from.as_deref(),
+            to: self.to.as_deref(),
+            jobs: self.jobs,
+        };
         ParsedArguments::SumArgs(args)
     }
 }
@@ -238,20 +811,26 @@ impl IntoArguments for LoginUser {
         ParsedArguments::LoginArgs(LoginArgs {
             name: &self.name,
             api_key: &self.api_key,
+            no_verify: self.no_verify,
         })
     }
 }
 
 impl IntoArguments for SendPixel {
     fn into_args(&self) -> ParsedArguments {
-        let date = &self.date;
-        let graph = &self.graph_id;
-        let quantity = &self.quantity;
-        ParsedArguments::PixelArgs(PixelArgs {
-            graph,
-            date: date.as_deref(),
-            quantity,
-        })
+        let date = self.date.as_deref();
+        let (note, data) = (self.note.as_deref(), self.data.as_deref());
+        match self.pixels.as_slice() {
+            // the original "send <graph> <quantity>" form
+            [graph, quantity] if !graph.contains('=') && !quantity.contains('=') => ParsedArguments::PixelArgs(PixelArgs {
+                graph,
+                date,
+                quantity,
+                note,
+                data,
+            }),
+            pixels => ParsedArguments::BatchArgs(BatchArgs { date, pixels: pixels.iter().map(String::as_str).collect(), note, data }),
+        }
     }
 }
 
@@ -263,6 +842,8 @@ impl IntoArguments for GetPixel {
             date: date.as_deref(),
             graph,
             quantity: "0",
+            note: None,
+            data: None,
         })
     }
 }
@@ -0,0 +1,94 @@
+/*
+ Shell completion scripts generated from the clap arguments, bash, zsh and fish scripts also
+ complete graph ids and sum graph names by calling the hidden `habitcli complete-graphs`
+ */
+use clap::CommandFactory;
+use clap_complete::Shell;
+
+use crate::args::HabitCLIArgs;
+use crate::user_data::{AliasesStruct, SumGraphsStruct, User};
+
+const BASH_GRAPHS: &str = r#"
+_habitcli_graphs() {
+    local cur="${COMP_WORDS[COMP_CWORD]}" prev="${COMP_WORDS[COMP_CWORD-1]}"
+    case "${COMP_WORDS[1]}" in
+        send|get|streak|remove|notes)
+            if [[ "$prev" == "${COMP_WORDS[1]}" && "$cur" != -* ]]; then
+                COMPREPLY=( $(compgen -W "$(habitcli -o plain complete-graphs 2>/dev/null)" -- "$cur") )
+                return 0
+            fi ;;
+        sum)
+            if [[ "$prev" == "-n" || "$prev" == "--name" ]]; then
+                COMPREPLY=( $(compgen -W "$(habitcli -o plain complete-graphs --sum 2>/dev/null)" -- "$cur") )
+                return 0
+            fi ;;
+    esac
+    _habitcli "$@"
+}
+complete -F _habitcli_graphs -o nosort -o bashdefault -o default habitcli
+"#;
+
+const ZSH_GRAPHS: &str = r#"
+_habitcli_graphs() {
+    case "${words[2]}" in
+        send|get|streak|remove|notes)
+            if (( CURRENT == 3 )) && [[ "${words[CURRENT]}" != -* ]]; then
+                compadd -- ${(f)"$(habitcli -o plain complete-graphs 2>/dev/null)"}
+                return
+            fi ;;
+        sum)
+            if [[ "${words[CURRENT-1]}" == (-n|--name) ]]; then
+                compadd -- ${(f)"$(habitcli -o plain complete-graphs --sum 2>/dev/null)"}
+                return
+            fi ;;
+    esac
+    _habitcli "$@"
+}
+compdef _habitcli_graphs habitcli
+"#;
+
+const FISH_GRAPHS: &str = r#"
+complete -c habitcli -n "__fish_seen_subcommand_from send get streak remove notes; and test (count (commandline -opc)) -eq 2" -f -a "(habitcli -o plain complete-graphs 2>/dev/null)"
+complete -c habitcli -n "__fish_seen_subcommand_from sum" -s n -l name -f -r -a "(habitcli -o plain complete-graphs --sum 2>/dev/null)"
+"#;
+
+pub fn script(shell: Shell) -> String {
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut HabitCLIArgs::command(), "habitcli", &mut script);
+    let mut script = String::from_utf8(script).expect("Completion scripts are valid utf-8");
+    match shell {
+        Shell::Bash => script.push_str(BASH_GRAPHS),
+        Shell::Zsh => script.push_str(ZSH_GRAPHS),
+        Shell::Fish => script.push_str(FISH_GRAPHS),
+        // other shells only get the static completion
+        _ => {}
+    }
+    script
+}
+
+// graph ids come from the cached graph list and aliases, completion never waits for pixela
+pub fn graph_ids(sum: bool) -> Vec<String> {
+    if sum {
+        return SumGraphsStruct::load()
+            .map(|sum_graphs| sum_graphs.sum_graphs.into_iter().map(|graph| graph.sum_graph_name).collect())
+            .unwrap_or_default();
+    }
+    let mut graphs = User::new().and_then(|user| user.get_cached_graph_list()).unwrap_or_default();
+    graphs.extend(AliasesStruct::load().map(|aliases| aliases.aliases.into_keys().collect::<Vec<_>>()).unwrap_or_default());
+    graphs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripts() {
+        let bash = script(Shell::Bash);
+        assert!(bash.contains("_habitcli()"));
+        assert!(bash.contains("complete -F _habitcli_graphs"));
+        assert!(script(Shell::Zsh).ends_with("compdef _habitcli_graphs habitcli\n"));
+        assert!(script(Shell::Fish).contains("complete-graphs --sum"));
+        assert!(!script(Shell::Elvish).contains("-o plain complete-graphs"));
+    }
+}
@@ -2,6 +2,16 @@
 
 Error handling and the Error type for the project, all errors converted into standarized output
 
+Exit codes of the process:
+    0 - success
+    1 - other errors
+    2 - invalid input (also used by clap for wrong arguments)
+    3 - authentication, not logged in or credentials rejected by Pixela
+    4 - network failure
+    5 - Pixela rejected the request
+    6 - local storage or config failure
+    7 - some of the work failed, details are in the output
+
  */
 use std::fmt::{Debug, Display};
     
@@ -11,6 +21,68 @@ pub enum Error {
     ReqwestError(reqwest::Error),
     PixelaError(String),
     SumGraphError(SumGraphError),
+    AuthError(String),
+    InvalidInput(String),
+    IoError(std::io::Error),
+    SledError(sled::Error),
+    // a config file that couldn't be parsed, file is its name in the config directory
+    TomlError { file: String, source: toml::de::Error },
+    TomlSerializeError(toml::ser::Error),
+    JsonError(serde_json::Error),
+    // reminder methods that failed, one message each
+    NotificationError(Vec<String>),
+}
+
+pub const EXIT_OTHER: u8 = 1;
+pub const EXIT_INVALID_INPUT: u8 = 2;
+pub const EXIT_AUTH: u8 = 3;
+pub const EXIT_NETWORK: u8 = 4;
+pub const EXIT_PIXELA: u8 = 5;
+pub const EXIT_STORAGE: u8 = 6;
+pub const EXIT_PARTIAL_FAILURE: u8 = 7;
+
+impl Error {
+    pub fn toml(file: &str, source: toml::de::Error) -> Error {
+        Error::TomlError { file: file.to_string(), source }
+    }
+
+    pub fn exit_code(&self) -> u8 {
+        use Error::*;
+        match self {
+            MissingEntryInDatabase(_) | TroubleSavingLoginInfo(_) => EXIT_STORAGE,
+            IoError(_) | SledError(_) | TomlError { .. } | TomlSerializeError(_) => EXIT_STORAGE,
+            ReqwestError(err) if err.is_decode() => EXIT_PIXELA,
+            ReqwestError(_) => EXIT_NETWORK,
+            PixelaError(_) | JsonError(_) => EXIT_PIXELA,
+            SumGraphError(err) => match err.kind {
+                SumGraphErrorKind::ErrorIOFile(_) => EXIT_STORAGE,
+                _ => EXIT_INVALID_INPUT,
+            },
+            AuthError(_) => EXIT_AUTH,
+            InvalidInput(_) => EXIT_INVALID_INPUT,
+            NotificationError(_) => EXIT_OTHER,
+        }
+    }
+
+    // what the user can do about the error, printed under it
+    pub fn hint(&self) -> Option<String> {
+        use Error::*;
+        let hint = match self {
+            AuthError(_) => "Run `habitcli login <name> <api-key>` with your Pixela username and token.",
+            ReqwestError(err) if err.is_timeout() => "Pixela didn't answer in time, try again or raise `--timeout`.",
+            ReqwestError(err) if err.is_connect() => "Couldn't reach Pixela, check your internet connection.",
+            ReqwestError(err) if err.is_decode() => "Pixela sent an unexpected response, try again later.",
+            JsonError(_) => "Pixela sent an unexpected response, try again later.",
+            PixelaError(msg) if msg.contains("graph") && (msg.contains("not found") || msg.contains("not exist")) => "Check the graph id with `habitcli list`.",
+            PixelaError(msg) if msg.contains("retry") => "Pixela rejects some requests of non supporters at random, run the command again.",
+            SumGraphError(err) => return err.hint(),
+            IoError(_) | SledError(_) => "Check that the habitCLI config and data directories (or HABITCLI_HOME) are writable, and that no other habitcli command (like the daemon) is holding the database.",
+            TomlError { file, .. } => return Some(format!("Fix or remove {file} in the habitCLI config directory.")),
+            NotificationError(_) => "Check the methods and the hook in the notifications of config.toml.",
+            _ => return None,
+        };
+        Some(hint.to_string())
+    }
 }
 #[derive(Debug)]
 pub enum SumGraphErrorKind {
@@ -19,6 +91,8 @@ pub enum SumGraphErrorKind {
     IncorrectNames,
     GraphNotFoundLocally,
     GraphsSumEachOther,
+    InvalidDateRange,
+    ConfigMismatch(Vec<String>),
 
 }
 
@@ -35,10 +109,23 @@ impl SumGraphError {
             SumGraphErrorKind::IncorrectNames => Some(String::from("Graphs with such names do not exist for your username.")),
             SumGraphErrorKind::GraphNotFoundLocally => Some(String::from("Couldn't locate a sum graph with provided name in your config.")),
             SumGraphErrorKind::GraphsSumEachOther => Some(String::from("Graphs sum each other which leads to unexpected behaviour, ex: SumGraphA {SumGraphB...}, SumGraphB{SumGraphA...}")),
+            SumGraphErrorKind::ConfigMismatch(ref problems) => Some(format!("Found {} problem(s) with your sum graph config, run setup-sum again or fix sum_graph.toml.\n{}", problems.len(), problems.join("\n"))),
+            SumGraphErrorKind::InvalidDateRange => Some(String::from("Invalid date range, use \"yyyymmdd\" dates where --from is not after --to.")),
         };
 
         SumGraphError { kind, msg }
     }
+
+    fn hint(&self) -> Option<String> {
+        let hint = match self.kind {
+            SumGraphErrorKind::IncorrectNames => "Check graph ids with `habitcli list`.",
+            SumGraphErrorKind::GraphNotFoundLocally => "See your sum graphs with `habitcli data`.",
+            SumGraphErrorKind::RepeatingNames | SumGraphErrorKind::GraphsSumEachOther => "Run `habitcli setup-sum` again with different graphs.",
+            SumGraphErrorKind::ConfigMismatch(_) => "Run `habitcli setup-sum check` after fixing the graphs.",
+            _ => return None,
+        };
+        Some(hint.to_string())
+    }
 }
 impl Display for SumGraphError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -48,6 +135,14 @@ impl Display for SumGraphError {
         }
     }
 }
+impl std::error::Error for SumGraphError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match &self.kind {
+            SumGraphErrorKind::ErrorIOFile(err) => Some(err),
+            _ => None,
+        }
+    }
+}
 
 impl Debug for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -58,6 +153,14 @@ impl Debug for Error {
             ReqwestError(err) => write!(f, "{:?}", err),
             PixelaError(msg) => write!(f, "Api call failed! Pixela responded with: {}",msg),
             SumGraphError(err) => write!(f, "{:?}", err), 
+            AuthError(msg) => write!(f, "Authentication failed: {}", msg),
+            InvalidInput(msg) => write!(f, "Invalid input: {}", msg),
+            IoError(err) => write!(f, "{:?}", err),
+            SledError(err) => write!(f, "{:?}", err),
+            TomlError { file, source } => write!(f, "{file}: {:?}", source),
+            TomlSerializeError(err) => write!(f, "{:?}", err),
+            JsonError(err) => write!(f, "{:?}", err),
+            NotificationError(failures) => write!(f, "Reminders failed: {:?}", failures),
         }
     }
 }
@@ -71,10 +174,37 @@ impl Display for Error {
             ReqwestError(err) => write!(f, "{}", err),
             PixelaError(msg) => write!(f, "Api call failed! Pixela responded with: {}",msg),
             SumGraphError(err) => write!(f, "{}", err), 
-
+            AuthError(msg) => write!(f, "Authentication failed: {}", msg),
+            InvalidInput(msg) => write!(f, "{}", msg),
+            IoError(err) => write!(f, "Couldn't access local files: {err}"),
+            SledError(err) => write!(f, "Local database failed: {err}"),
+            TomlError { file, source } => write!(f, "Failed loading {file}: {}", source.message()),
+            TomlSerializeError(err) => write!(f, "Failed saving config: {err}"),
+            JsonError(err) => write!(f, "Couldn't read the response from Pixela: {err}"),
+            NotificationError(failures) => write!(f, "Some reminders failed: {}", failures.join(" ")),
         }
     }
 }
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use Error::*;
+        match self {
+            ReqwestError(err) => Some(err),
+            SumGraphError(err) => Some(err),
+            IoError(err) => Some(err),
+            SledError(err) => Some(err),
+            TomlError { source, .. } => Some(source),
+            TomlSerializeError(err) => Some(err),
+            JsonError(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Self::ReqwestError(err)
+    }
+}
 impl From<&str> for Error {
     fn from(string: &str) -> Self {
         Self::MissingEntryInDatabase(string.to_string())
@@ -82,15 +212,61 @@ impl From<&str> for Error {
 }
 impl From<std::io::Error> for Error {
     fn from(err: std::io::Error) -> Self {
-        Self::MissingEntryInDatabase(err.to_string())
+        Self::IoError(err)
     }
 }
 impl From<sled::Error> for Error {
     fn from(err: sled::Error) -> Self {
         match err {
-            sled::Error::Io(io_err) => Error::from(io_err),
-            _ => err.into(),
+            sled::Error::Io(io_err) => Self::IoError(io_err),
+            _ => Self::SledError(err),
         }
     }
 }
+impl From<toml::ser::Error> for Error {
+    fn from(err: toml::ser::Error) -> Self {
+        Self::TomlSerializeError(err)
+    }
+}
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Self::JsonError(err)
+    }
+}
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_codes() {
+        assert_eq!(Error::AuthError("Log in first.".to_string()).exit_code(), EXIT_AUTH);
+        assert_eq!(Error::PixelaError("Graph not found".to_string()).exit_code(), EXIT_PIXELA);
+        assert_eq!(Error::InvalidInput("Wrong type".to_string()).exit_code(), EXIT_INVALID_INPUT);
+        assert_eq!(Error::MissingEntryInDatabase("Failed loading".to_string()).exit_code(), EXIT_STORAGE);
+        assert_eq!(Error::SumGraphError(SumGraphError::new(SumGraphErrorKind::RepeatingNames)).exit_code(), EXIT_INVALID_INPUT);
+        let io = std::io::Error::new(std::io::ErrorKind::NotFound, "sum_graph.toml");
+        assert_eq!(Error::SumGraphError(SumGraphError::new(SumGraphErrorKind::ErrorIOFile(io))).exit_code(), EXIT_STORAGE);
+        assert_eq!(Error::from(sled::Error::Unsupported("old format".to_string())).exit_code(), EXIT_STORAGE);
+    }
+
+    #[test]
+    fn sources_and_hints() {
+        use std::error::Error as _;
+
+        let io = std::io::Error::new(std::io::ErrorKind::PermissionDenied, "habitCLI");
+        let err = Error::from(sled::Error::Io(io));
+        assert!(matches!(err, Error::IoError(_)));
+        assert!(err.source().is_some());
+
+        let err = Error::toml("goals.toml", toml::from_str::<toml::Value>("goals = [").unwrap_err());
+        assert!(err.to_string().starts_with("Failed loading goals.toml"));
+        assert_eq!(err.hint().unwrap(), "Fix or remove goals.toml in the habitCLI config directory.");
+        assert!(err.source().is_some());
+
+        assert!(Error::AuthError("Log in first.".to_string()).hint().unwrap().contains("habitcli login"));
+        assert!(Error::PixelaError("Specified graph not found.".to_string()).hint().unwrap().contains("habitcli list"));
+        assert_eq!(Error::InvalidInput("Wrong type".to_string()).hint(), None);
+    }
+}
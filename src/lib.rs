@@ -2,15 +2,23 @@ pub mod args;
 pub mod pixela;
 pub mod user_data;
 pub mod error;
+pub mod notification;
+pub mod output;
+pub mod completions;
+pub mod tui;
 
-use std::{fmt::Display, io::stdin, sync::Arc};
+use std::{collections::HashSet, io::{stdin, Read}, sync::Arc, time::Duration};
 
 use error::{Error, Result, SumGraphError, SumGraphErrorKind};
 
-use args::{CreateGraphArgs, LoginArgs, NewUserArgs, PixelArgs, RemoveArgs, StreakGetArgs, SumArgs, SumGraphArgs};
+use args::{AccountArgs, AliasArgs, BatchArgs, CadenceArgs, ConfigArgs, CreateGraphArgs, GoalArgs, LoginArgs, NewUserArgs, PixelArgs, RemoveArgs, RestArgs, StreakGetArgs, SumArgs, SumGraphArgs, TimerArgs};
 use pixela::*;
-use tokio::{sync::Mutex, task::JoinHandle};
-use user_data::{SumGraphStruct, SumGraphsStruct};
+use chrono::{Days, NaiveDate, TimeDelta};
+use serde::{de::DeserializeOwned, Serialize};
+use std::future::Future;
+use output::{GoalProgress, GraphAlias, Output, OutputFormat, PixelNote, SentPixel, Setting, SummedDay};
+use tokio::{sync::{Mutex, Semaphore}, task::{JoinHandle, JoinSet}};
+use user_data::{AliasesStruct, CadencesStruct, Goal, GoalPeriod, GoalsStruct, RemindersStruct, SettingsStruct, SumGraphStruct, SumGraphsStruct, Timer};
 pub struct Worker {
     /*
     Worker struct that calls all the functions 
@@ -20,213 +28,893 @@ pub struct Worker {
     api_key: Option<String>,
     name: Option<String>,
     sum_graphs: Option<SumGraphsStruct>,
+    // skip the local cache of pixela responses
+    refresh: bool,
+    // config.toml, defaults until login
+    settings: SettingsStruct,
 }
-impl Display for Worker {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f,"Username: {}\nSum Graphs Info:\n{}", self.name.as_ref().unwrap(), self.sum_graphs.as_ref().unwrap()) 
-        }
-    }
 impl Worker {
     pub fn new(session: Session) -> Worker {
         Worker {
             session,
             api_key: None,
             name: None,
-            sum_graphs: None
+            sum_graphs: None,
+            refresh: false,
+            settings: SettingsStruct::default(),
         }
     }
+    pub fn with_refresh(mut self, refresh: bool) -> Worker {
+        self.refresh = refresh;
+        self
+    }
     pub fn login(&mut self) -> Result<()> {
         // gets data from local database and saves it in the struct
-        let user = user_data::User::new();
+        let user = user_data::User::new()?;
         let data = user.get_user_data()?;
         let (name, token) = (data.name, data.token);
         self.api_key = Some(token);
         self.name = Some(name);
         self.sum_graphs =  SumGraphsStruct::load().ok();
-        
+        self.load_settings()
+    }
+    pub fn load_settings(&mut self) -> Result<()> {
+        let settings = SettingsStruct::load()?;
+        self.session = std::mem::take(&mut self.session)
+            .with_base_url(&settings.api_url)
+            .with_retries(settings.retry.attempts, Duration::from_millis(settings.retry.delay_ms));
+        self.settings = settings;
         Ok(())
     }
+    pub fn settings(&self) -> &SettingsStruct {
+        &self.settings
+    }
 
-    pub async fn call_send(&self, args: PixelArgs<'_>) {
-        let graph = args.graph;
-        let date = args.date;
-        let quantity = &args.quantity;
+    pub async fn call_send(&self, args: PixelArgs<'_>) -> Result<Output> {
+        let graph = &resolve_graph(args.graph)?;
+        let quantity = args.quantity;
+        let date: String = match args.date {
+            Some(date) => date.to_string(),
+            None => self.settings.today().format("%Y%m%d").to_string(),
+        };
         let name = &self.name.to_owned().expect("Data should be there");
         let api_key = &self.api_key.to_owned().expect("Data should be there");
-        let url = &self.create_url_graph(graph, &name);
-        match &self
-            .session
-            .send_pixel(url, quantity, date.as_deref(), &api_key).await
-        {
-            Ok(call_result) => {
-                if let CallResult::ApiResponse(msg) = call_result {
-                    msg.out_message();
+        let url = &self.session.graph_url(name, graph);
+        let optional_data = optional_data(args.note, args.data)?;
+        let message = send_checked(&self.session, url, quantity, &date, optional_data.as_deref(), api_key).await?;
+        let (summed, sum_error) = match self.refresh_dependent_sums(std::slice::from_ref(graph), &date).await {
+            Ok(summed) => (summed, None),
+            Err(e) => (Vec::new(), Some(e.to_string())),
+        };
+        forget_pixels(summed.iter().map(String::as_str).chain([graph.as_str()]));
+        Ok(Output::Sent { graph: graph.to_string(), date, quantity: quantity.to_string(), message, summed, sum_error })
+    }
+    pub async fn call_send_batch(&self, args: BatchArgs<'_>) -> Result<Output> {
+        self.send_batch(args, false).await
+    }
+    // sum graphs depending on the sent graphs are recomputed when auto_sum is on, or always
+    async fn send_batch(&self, args: BatchArgs<'_>, sum_always: bool) -> Result<Output> {
+        let date: String = match args.date {
+            Some(date) => date.to_string(),
+            None => self.settings.today().format("%Y%m%d").to_string(),
+        };
+        let name = self.name.clone().expect("Data should be there");
+        let api_key = self.api_key.clone().expect("Data should be there");
+        let aliases = AliasesStruct::load()?;
+        let optional_data = optional_data(args.note, args.data)?;
+        let mut entries: Vec<(String, String)> = Vec::new();
+        for pixel in args.pixels {
+            let (graph, quantity) = match pixel.split_once('=') {
+                Some((graph, quantity)) if !graph.is_empty() && !quantity.is_empty() => (aliases.resolve(graph).to_string(), quantity.to_string()),
+                _ => return Err(Error::InvalidInput(format!("Expected \"graph=quantity\", got \"{pixel}\". (habitcli send -h)"))),
+            };
+            // two sends to the same pixel would race each other
+            if entries.iter().any(|(sent, _)| *sent == graph) {
+                return Err(Error::InvalidInput(format!("{graph} is listed more than once.")));
+            }
+            entries.push((graph, quantity));
+        }
+        if entries.is_empty() {
+            return Err(Error::InvalidInput("Nothing to send, pass \"graph=quantity\" pairs.".to_string()));
+        }
+
+        // every graph is independent so all of them are sent at the same time
+        let mut tasks: JoinSet<(usize, Result<String>)> = JoinSet::new();
+        for (index, (graph, quantity)) in entries.iter().enumerate() {
+            let session = self.session.clone();
+            let url = session.graph_url(&name, graph);
+            let (quantity, date, api_key, optional_data) = (quantity.clone(), date.clone(), api_key.clone(), optional_data.clone());
+            tasks.spawn(async move {
+                (index, send_checked(&session, &url, &quantity, &date, optional_data.as_deref(), &api_key).await)
+            });
+        }
+        let mut results: Vec<(usize, Result<String>)> = Vec::new();
+        while let Some(joined) = tasks.join_next().await {
+            results.push(joined.expect("Sending task panicked"));
+        }
+        results.sort_by_key(|(index, _)| *index);
+
+        let pixels: Vec<SentPixel> = results.into_iter().map(|(index, result)| SentPixel {
+            graph: entries[index].0.clone(),
+            quantity: entries[index].1.clone(),
+            error: result.err().map(|err| err.to_string()),
+        }).collect();
+        let sent: Vec<String> = pixels.iter().filter(|pixel| pixel.error.is_none()).map(|pixel| pixel.graph.clone()).collect();
+        let (summed, sum_error) = match self.sum_dependents(&sent, &date, sum_always).await {
+            Ok(summed) => (summed, None),
+            Err(e) => (Vec::new(), Some(e.to_string())),
+        };
+        forget_pixels(summed.iter().chain(&sent).map(String::as_str));
+        Ok(Output::Batch { date, pixels, summed, sum_error })
+    }
+    pub async fn call_log(&self, date: Option<&str>) -> Result<Output> {
+        // "graph=quantity" pairs, one per line, lines starting with # are skipped
+        let mut input = String::new();
+        stdin().read_to_string(&mut input)?;
+        let pixels: Vec<&str> = input.lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect();
+        self.call_send_batch(BatchArgs { date, pixels, note: None, data: None }).await
+    }
+    pub async fn call_checkin(&self, date: Option<&str>, graphs: &[String]) -> Result<Output> {
+        let date = match date {
+            Some(date) => NaiveDate::parse_from_str(date, "%Y%m%d").map_err(|_| Error::InvalidInput(format!("Invalid date {date}, use \"yyyymmdd\".")))?,
+            None => self.settings.today(),
+        };
+        let yesterday = date - chrono::Days::new(1);
+        let aliases = AliasesStruct::load()?;
+        let definitions = self.live_graph_definitions().await?;
+        let graphs: Vec<String> = match graphs {
+            [] => user_data::CheckinStruct::load()?.graphs,
+            graphs => graphs.to_vec(),
+        };
+        let graphs: Vec<&GraphDefinition> = match graphs.is_empty() {
+            true => definitions.iter().collect(),
+            false => graphs.iter().map(|graph| {
+                let graph = aliases.resolve(graph);
+                definitions.iter().find(|definition| definition.id == graph)
+                    .ok_or_else(|| Error::InvalidInput(format!("There is no graph {graph} on your account.")))
+            }).collect::<Result<_>>()?,
+        };
+
+        eprintln!("Checking in for {}. Press enter to keep the value in brackets, type \"s\" to skip a graph.", self.settings.format_date(date));
+        let mut pixels: Vec<String> = Vec::new();
+        for graph in graphs {
+            let history = self.pixels(&graph.id).await?;
+            if history.iter().any(|pixel| pixel.date == date) {
+                continue;
+            }
+            let default = history.iter().find(|pixel| pixel.date == yesterday).map(|pixel| pixel.quantity.to_string());
+            loop {
+                match &default {
+                    Some(default) => eprint!("{} ({}) [{default}]: ", graph.id, graph.unit),
+                    None => eprint!("{} ({}): ", graph.id, graph.unit),
                 }
+                let mut input = String::new();
+                // end of input skips the remaining graphs but keeps the answers given so far
+                if stdin().read_line(&mut input)? == 0 {
+                    eprintln!();
+                    return self.send_checkin(date, pixels).await;
+                }
+                match checkin_answer(input.trim(), default.as_deref(), &graph.number_type) {
+                    Ok(Some(quantity)) => pixels.push(format!("{}={quantity}", graph.id)),
+                    Ok(None) => {}
+                    Err(msg) => {
+                        eprintln!("{msg}");
+                        continue;
+                    }
+                }
+                break;
             }
-            Err(e) => println!("There was an error. {:?}", e),
+        }
+        self.send_checkin(date, pixels).await
+    }
+    async fn send_checkin(&self, date: NaiveDate, pixels: Vec<String>) -> Result<Output> {
+        if pixels.is_empty() {
+            return Ok(Output::message(format!("Nothing to send for {}.", self.settings.format_date(date))));
+        }
+        let date = date.format("%Y%m%d").to_string();
+        // the check-in ends with the sums whether or not auto_sum is on
+        self.send_batch(BatchArgs { date: Some(&date), pixels: pixels.iter().map(String::as_str).collect(), note: None, data: None }, true).await
+    }
+    // optional data of a pixel, sent again when only its quantity changes
+    async fn pixel_optional_data(&self, graph: &str, date: NaiveDate) -> Result<Option<String>> {
+        let name = self.name.as_deref().expect("Data should be there");
+        let api_key = self.api_key.as_deref().expect("Data should be there");
+        Ok(self.session.get_pixel(name, api_key, graph, date).await?.and_then(|pixel| pixel.optional_data))
+    }
+    // deletes a pixel, sum graphs depending on it are updated like after a send
+    async fn clear_pixel(&self, graph: &str, date: &str) -> Result<Vec<String>> {
+        let name = self.name.as_deref().expect("Data should be there");
+        let api_key = self.api_key.as_deref().expect("Data should be there");
+        self.session.delete_pixel(&self.session.graph_url(name, graph), date, api_key).await?;
+        let summed = self.refresh_dependent_sums(&[graph.to_string()], date).await?;
+        forget_pixels(summed.iter().map(String::as_str).chain([graph]));
+        Ok(summed)
+    }
+    async fn refresh_dependent_sums(&self, graphs: &[String], date: &str) -> Result<Vec<String>> {
+        // recomputes sum graphs that depend on graphs that were just written, if enabled in sum_graph.toml
+        self.sum_dependents(graphs, date, false).await
+    }
+    async fn sum_dependents(&self, graphs: &[String], date: &str, always: bool) -> Result<Vec<String>> {
+        let dependents = match self.sum_graphs.as_ref() {
+            Some(sum_graphs) => sum_graphs.to_resum(graphs, always),
+            None => return Ok(Vec::new()),
         };
+        if dependents.is_empty() {
+            return Ok(Vec::new());
+        }
+        let name = self.name.as_deref().expect("Data should be there");
+        let api_key = self.api_key.as_deref().expect("Data should be there");
+        sum_graphs_for_date(&self.session, name, api_key, &dependents, date, false).await
     }
-    pub async fn handle_sum_graph(&self, args: SumArgs<'_>) -> Result<()>{
+    pub async fn handle_sum_graph(&self, args: SumArgs<'_>) -> Result<Output>{
         let name = self.name.clone().expect("Should be logged in");
         let api_key = self.api_key.clone().expect("Should be logged in");
         let graphs = if let Some(graphs) = self.sum_graphs.as_ref() {
             graphs
         } else { return Err(Error::MissingEntryInDatabase("Sum graphs are not properly set up".to_string())) };
-        let commits = Arc::new(Mutex::new(0));
-        let mut tasks: Vec<JoinHandle<Result<()>>> = Vec::new();
+
+        // sum graphs are already ordered so that dependencies come first, filtering keeps that order
+        let specified_name = args.name.map(resolve_graph).transpose()?;
+        let selected: Vec<SumGraphStruct> = graphs.sum_graphs.iter()
+            .filter(|graph| specified_name.as_ref().is_none_or(|specified_name| &graph.sum_graph_name == specified_name))
+            .cloned()
+            .collect();
+        if selected.is_empty() {
+            return Err(Error::SumGraphError(SumGraphError::new(SumGraphErrorKind::GraphNotFoundLocally)));
+        }
+
+        if let (Some(from), Some(to)) = (args.from, args.to) {
+            return self.backfill_sum_graphs(selected, from, to, args.jobs).await;
+        }
+
         let date: String = match args.date {
             Some(date) => date.to_string(),
-            None => chrono::Local::now().format("%Y%m%d").to_string(),
+            None => self.settings.today().format("%Y%m%d").to_string(),
         };
-        let mut done_anything = false;
+        let summed = sum_graphs_for_date(&self.session, &name, &api_key, &selected, &date, false).await?;
+        forget_pixels(summed.iter().map(String::as_str));
+        Ok(Output::Sums { days: vec![SummedDay { date, summed, error: None }] })
+    }
 
-        for graph in &graphs.sum_graphs {
+    async fn backfill_sum_graphs(&self, selected: Vec<SumGraphStruct>, from: &str, to: &str, jobs: usize) -> Result<Output> {
+        let name = self.name.clone().expect("Should be logged in");
+        let api_key = self.api_key.clone().expect("Should be logged in");
+        let days = date_range(from, to)?;
+        let selected = Arc::new(selected);
+        let permits = Arc::new(Semaphore::new(jobs.max(1)));
+        let mut tasks: JoinSet<(String, Result<Vec<String>>)> = JoinSet::new();
 
-            if let Some(specified_name) = args.name {
-                if graph.sum_graph_name != specified_name {
-                    continue;
-                }
-            };
+        // every day is independent so days run concurrently, graphs inside of a day stay in order
+        for day in days {
+            let session = self.session.clone();
+            let (name, api_key) = (name.clone(), api_key.clone());
+            let selected = Arc::clone(&selected);
+            let permits = Arc::clone(&permits);
+            tasks.spawn(async move {
+                let _permit = permits.acquire_owned().await.expect("Semaphore is never closed");
+                let result = sum_graphs_for_date(&session, &name, &api_key, &selected, &day, true).await;
+                (day, result)
+            });
+        }
 
-            for graph_name in &graph.graphs_to_sum {
-                let name = name.clone();
-                let api_key = api_key.clone();
-                let date = date.clone();
-                let commits = Arc::clone(&commits);
-                let graph_name = graph_name.clone();
-                let handle = tokio::spawn(async move {
-                    let url = format!("https://pixe.la/v1/users/{}/graphs/{}",name, graph_name);
-                    Session::async_get_graph_val(&url, &date, &api_key.clone(), commits.clone()).await?;
-                    Ok(())
-                });
-                tasks.push(handle);
-            }   
-            for _ in 0..tasks.len() {
-                let popped = tasks.pop().unwrap();
-                popped.await.unwrap()?;
-            }
-            let url = format!("https://pixe.la/v1/users/{}/graphs/{}",name, graph.sum_graph_name);
-            let sendable_commits = commits.lock().await.to_string();
-            self.session.send_pixel(&url, &sendable_commits, args.date, &api_key).await?;
-            println!("Summed {}.", graph.sum_graph_name);
-            *commits.lock().await = 0;
-            done_anything = true;
-        }
-
-        if !done_anything {
-            return Err(Error::SumGraphError(SumGraphError::new(SumGraphErrorKind::GraphNotFoundLocally)));
+        let mut days = Vec::new();
+        while let Some(joined) = tasks.join_next().await {
+            let (date, result) = joined.expect("Summing task panicked");
+            days.push(match result {
+                Ok(summed) => SummedDay { date, summed, error: None },
+                Err(err) => SummedDay { date, summed: Vec::new(), error: Some(err.to_string()) },
+            });
         }
-        println!("Success! Your Sum Graph has been updated.");
-        return Ok(());
+        days.sort_by(|a, b| a.date.cmp(&b.date));
+        forget_pixels(selected.iter().map(|graph| graph.sum_graph_name.as_str()));
+        Ok(Output::Sums { days })
     }
         
 
-    pub async fn call_get(&self, args: PixelArgs<'_>) {
-        let graph = args.graph;
-        let date = args.date;
+    pub async fn call_get(&self, args: PixelArgs<'_>) -> Result<Output> {
+        let graph = &resolve_graph(args.graph)?;
+        let date = match args.date {
+            Some(date) => NaiveDate::parse_from_str(date, "%Y%m%d").map_err(|_| Error::InvalidInput(format!("Invalid date {date}, use \"yyyymmdd\".")))?,
+            None => self.settings.today(),
+        };
         let name = &self.name.to_owned().expect("Data should be there");
         let api_key = &self.api_key.to_owned().expect("Data should be there");
-        let url = &self.create_url_graph(graph, &name);
-        match &self
-            .session
-            .get_pixel_info(url, graph, date.as_deref(), &api_key).await
+        let pixel = self.cached(&user_data::pixel_key(graph, date), user_data::PIXELS_TTL, self.session.get_pixel(name, api_key, graph, date)).await?;
+        Ok(Output::Pixel {
+            graph: graph.to_string(),
+            date: date.format("%Y%m%d").to_string(),
+            quantity: pixel.as_ref().map_or(0.0, |pixel| pixel.quantity),
+            note: pixel.as_ref().and_then(Pixel::note),
+        })
+    }
+    pub async fn call_notes(&self, graph: &str, search: Option<&str>, from: Option<&str>) -> Result<Output> {
+        let graph = &resolve_graph(graph)?;
+        let search = search.map(str::to_lowercase);
+        let from = from.map(|from| NaiveDate::parse_from_str(from, "%Y%m%d").map_err(|_| Error::InvalidInput(format!("Invalid date {from}, use \"yyyymmdd\"."))))
+            .transpose()?;
+        let mut notes: Vec<PixelNote> = self.history(graph, from).await?.iter()
+            .filter_map(|pixel| pixel.note().map(|note| PixelNote { date: pixel.date.format("%Y%m%d").to_string(), quantity: pixel.quantity, note }))
+            .filter(|note| search.as_ref().is_none_or(|search| note.note.to_lowercase().contains(search)))
+            .collect();
+        notes.sort_by(|a, b| a.date.cmp(&b.date));
+        Ok(Output::Notes { graph: graph.to_string(), notes })
+    }
+    pub async fn call_save_data(&self, args: LoginArgs<'_>) -> Result<Output> {
+        let graphs = match args.no_verify {
+            true => None,
+            false => Some(self.check_credentials(args.name, args.api_key).await?),
+        };
+        let user = user_data::User::new()?;
+        user.set_user_data(args.name, args.api_key)?;
+        user.clear_cache()?;
+        match graphs {
+            Some(graphs) => {
+                let message = format!("Logged in as {}, you have {} graph(s).", args.name, graphs.len());
+                let _ = user.set_cached(user_data::GRAPHS_KEY, &graphs);
+                Ok(Output::message(message))
+            }
+            None => Ok(Output::message(format!("Logged in as {}, the credentials weren't checked.", args.name))),
+        }
+    }
+    pub fn call_logout(&self, yes: bool) -> Result<Output> {
+        let user = user_data::User::new()?;
+        let name = user.get_user_data()?.name;
+        let deleted = [
+            format!("  credentials of {name}"),
+            format!("  {} cached Pixela response(s)", user.cache_len()?),
+        ];
+        drop(user);
+        if !yes && !confirm(&format!("This deletes from this device:\n{}\nLog out?", deleted.join("\n")))? {
+            return Ok(Output::message("Nothing was deleted."));
+        }
+        let user = user_data::User::new()?;
+        user.remove_user_data()?;
+        user.clear_cache()?;
+        Ok(Output::message(format!("Logged out {name}.")))
+    }
+    pub fn call_reset(&self, yes: bool) -> Result<Output> {
+        if !user_data::local_data_exists()? {
+            return Ok(Output::message("There is no local habitCLI data."));
+        }
+        let mut deleted: Vec<String> = user_data::config_files()?.into_iter().map(|file| format!("  {file}")).collect();
         {
-            Ok(call_result) => {
-                if let CallResult::Heatmap(heatmap) = call_result {
-                    heatmap.out_heatmap_info();
-                }
+            let user = user_data::User::new()?;
+            if let Ok(data) = user.get_user_data() {
+                deleted.push(format!("  credentials of {}", data.name));
             }
-            Err(e) => println!("There was an error. {:?}", e),
-        };
+            deleted.push(format!("  {} cached Pixela response(s)", user.cache_len()?));
+            deleted.push(format!("  {} rest day(s)", user.get_rest_days()?.len()));
+            if let Some(timer) = user.get_timer()? {
+                deleted.push(format!("  the running timer of {}", timer.graph));
+            }
+        }
+        if !yes && !confirm(&format!("This deletes from this device:\n{}\nNothing on Pixela is touched. Reset?", deleted.join("\n")))? {
+            return Ok(Output::message("Nothing was deleted."));
+        }
+        user_data::remove_local_data()?;
+        Ok(Output::message("All local habitCLI data was deleted."))
     }
-    fn create_url_graph(&self, graph: &str, name: &str) -> String {
-        format!("https://pixe.la/v1/users/{name}/graphs/{graph}")
+    pub async fn call_whoami(&self) -> Result<Output> {
+        let name = self.name.as_deref().expect("Data should be there");
+        let api_key = self.api_key.as_deref().expect("Data should be there");
+        let graphs = self.check_credentials(name, api_key).await?;
+        let _ = user_data::User::new().and_then(|user| user.set_cached(user_data::GRAPHS_KEY, &graphs));
+        Ok(Output::message(format!("Logged in as {name}, Pixela accepts your token. You have {} graph(s), see your profile at {}.", graphs.len(), self.session.profile_url(name))))
     }
-    pub fn call_save_data(&self, args: LoginArgs) -> Result<()> {
-        let user = user_data::User::new();
-        Ok(user.set_user_data(args.name, args.api_key)?)
+    // lists graphs with the credentials, a rejection says whether the user or the token is wrong
+    async fn check_credentials(&self, name: &str, api_key: &str) -> Result<Vec<GraphDefinition>> {
+        match self.session.get_graph_definitions(api_key, &self.session.graphs_url(name)).await {
+            Err(Error::AuthError(msg)) => match self.session.user_exists(name).await {
+                Ok(false) => Err(Error::AuthError(format!("There is no Pixela user {name}."))),
+                Ok(true) => Err(Error::AuthError(format!("Pixela rejected the token of {name}."))),
+                Err(_) => Err(Error::AuthError(msg)),
+            },
+            result => result,
+        }
     }
-    pub async fn setup_graphs(&self, args: SumGraphArgs) -> Result<()> {
+    pub async fn setup_graphs(&self, args: SumGraphArgs) -> Result<Output> {
         let mut sum_graphs: Vec<SumGraphStruct> = vec![];
         let mut sum_graph_names_duplicate_tracker: Vec<String> = vec![];
 
-        let name = &self.name.to_owned().expect("Data should be there");
-        let api_key = &self.api_key.to_owned().expect("Data should be there");
-        let url = format!("https://pixe.la/v1/users/{name}/graphs/");
-        let correct_names = self.session.get_graph_list(api_key, &url).await;
-        let correct_names = if let Ok(CallResult::List(list)) = correct_names {
-            list
-        } else { return Err(Error::MissingEntryInDatabase("Unable to verify graph names, possibly graphs are non-existent".to_string()))};
-        input_graph_names(&mut sum_graphs, &mut sum_graph_names_duplicate_tracker, args.sum_graph_amount, &correct_names)?;
+        let correct_names: Vec<String> = self.live_graph_definitions().await?.into_iter().map(|graph| graph.id).collect();
+        let aliases = AliasesStruct::load()?;
+        input_graph_names(&mut sum_graphs, &mut sum_graph_names_duplicate_tracker, args.sum_graph_amount, &correct_names, &aliases)?;
             
             
-        SumGraphsStruct::build(sum_graphs)?.save()?;
-        println!("Sum Graphs saved locally. You can now use 'sum'.");
+        let mut sum_graphs = SumGraphsStruct::build(sum_graphs)?;
+        sum_graphs.auto_sum = args.auto_sum;
+        sum_graphs.save()?;
 
-        Ok(())
+        Ok(Output::message("Sum Graphs saved locally. You can now use 'sum'."))
     }
-    pub async fn call_list(&self) -> Result<()> {
-        let name = &self.name.to_owned().expect("Data should be there");
-        let api_key = &self.api_key.to_owned().expect("Data should be there");
-        let url = format!("https://pixe.la/v1/users/{name}/graphs/");
-        let graph_list = &self.session.get_graph_list(api_key, &url).await;
-        if let CallResult::List(list) = graph_list.as_ref().map_err(|e| Error::MissingEntryInDatabase(e.to_string()))?{
-            list.iter().for_each(|graph_id| println!("Graph Name: {}", graph_id.trim_matches('"')));
+    pub async fn check_sum_graphs(&self) -> Result<Output> {
+        let sum_graphs = match SumGraphsStruct::load() {
+            Ok(sum_graphs) => sum_graphs,
+            Err(Error::IoError(_)) => return Err(Error::MissingEntryInDatabase("Sum graphs are not properly set up".to_string())),
+            Err(err) => return Err(err),
         };
-
-        Ok(())
+        let definitions = self.live_graph_definitions().await?;
+        let problems = sum_graphs.check(&definitions);
+        if !problems.is_empty() {
+            return Err(Error::SumGraphError(SumGraphError::new(SumGraphErrorKind::ConfigMismatch(problems))));
+        }
+        Ok(Output::message("Sum graphs are valid for your account."))
     }
-    pub async fn call_create_user(&self, args: NewUserArgs<'_>) -> Result<()> {
-        let NewUserArgs{token, username, minor, tos} = args;
-        let _ = &self.session.create_user(token, username, minor, tos).await?;
-        println!("Success: Account created, from now on you are logged in on this device");
-        match &self.call_save_data(LoginArgs{name: username, api_key: token}) {
-            Ok(_) => (),
-            Err(err) => println!("Local database failure: {err}"),
+    pub async fn call_list(&self) -> Result<Output> {
+        let graphs = self.graph_definitions().await?.into_iter().map(|graph| graph.id).collect();
+        Ok(Output::Graphs { graphs, aliases: graph_aliases(AliasesStruct::load()?) })
+    }
+    pub async fn call_create_user(&self, args: NewUserArgs<'_>) -> Result<Output> {
+        let NewUserArgs{token, username, not_minor, agree_tos} = args;
+        // everything pixela would reject is checked before any question is asked
+        validate_username(username)?;
+        if let Some(token) = token {
+            validate_token(token)?;
         }
-        return Ok(());
+        let token = match token {
+            Some(token) => token.to_string(),
+            None => {
+                let token = prompt("Choose a token, it works as your password (8 to 128 printable ascii characters): ")?;
+                validate_token(&token)?;
+                token
+            }
+        };
+        let agree_tos = agree_tos || confirm("Do you agree to Pixela's terms of service? (https://github.com/a-know/Pixela/wiki/Terms-of-Service)")?;
+        let not_minor = not_minor || confirm("Are you an adult, or do you have your guardian's consent?")?;
+        self.session.create_user(&token, username, not_minor, agree_tos).await?;
 
+        // the new account is used once so a problem shows up now instead of on the next command
+        if let Err(err) = self.check_credentials(username, &token).await {
+            return Ok(Output::message(format!("Success: Account created, but Pixela didn't accept it yet, try `habitcli login {username} <token>` in a moment. {err}")));
+        }
+        match &self.call_save_data(LoginArgs{name: username, api_key: &token, no_verify: true}).await {
+            Ok(_) => Ok(Output::message("Success: Account created, from now on you are logged in on this device")),
+            Err(err) => Ok(Output::message(format!("Success: Account created, but logging in failed. Local database failure: {err}"))),
+        }
     }
-    pub async fn call_create_graph(&self, args: CreateGraphArgs<'_>) -> Result<()> {
+    pub async fn call_create_graph(&self, args: CreateGraphArgs<'_>) -> Result<Output> {
         let CreateGraphArgs{name, id, number_type, color, unit} = args;
+        let number_type = number_type.unwrap_or(&self.settings.graph.number_type);
+        let color = color.unwrap_or(&self.settings.graph.color);
         let username = &self.name.to_owned().expect("Data should be there");
         let token = &self.api_key.to_owned().expect("Data should be there");
-        self.session.create_graph(username, token, id, name, number_type, unit, color).await?;
-        println!("Success: New graph created, check it out at https://pixe.la/v1/users/{}/graphs/{}.html.", username, id);
-        return Ok(());
-
+        let graph = GraphDefinition { id: id.to_string(), name: name.to_string(), unit: unit.to_string(), number_type: number_type.to_string(), color: color.to_string() };
+        self.session.create_graph(username, token, &graph).await?;
+        update_cached_graphs(|graphs| graphs.push(graph.clone()));
+        Ok(Output::message(format!("Success: New graph created, check it out at {}.html.", self.session.graph_url(username, id))))
     }
-    pub async fn call_remove_graph(&self, args: RemoveArgs<'_> ) -> Result<()> {
+    pub async fn call_remove_graph(&self, args: RemoveArgs<'_> ) -> Result<Output> {
         let username = &self.name.to_owned().expect("Data should be there");
         let token = &self.api_key.to_owned().expect("Data should be there");
-        let graph_name = args.graph_name;
+        let graph_name = &resolve_graph(args.graph_name)?;
         self.session.remove_graph(username, token, graph_name).await?;
-        println!("Success: A graph has been removed from your account.");
-        return Ok(());
+        update_cached_graphs(|graphs| graphs.retain(|graph| &graph.id != graph_name));
+        forget_pixels([graph_name.as_str()]);
+        Ok(Output::message("Success: A graph has been removed from your account."))
     }
 
-    pub async fn call_streak(&self, args: StreakGetArgs<'_>) -> Result<()> {
-        let username = &self.name.to_owned().expect("Data should be there");
-        let token = &self.api_key.to_owned().expect("Data should be there");
-        let streak = self.session.get_streak(username, token, &args.graph_id).await?;
-        println!("{}", prepare_streak_string(streak, &args.graph_id));
-        Ok(())
+    pub async fn call_streak(&self, args: StreakGetArgs<'_>) -> Result<Output> {
+        let graph = &resolve_graph(args.graph_id)?;
+        let cadence = CadencesStruct::load()?.get(graph);
+        let rest_days: HashSet<NaiveDate> = user_data::User::new()?.get_rest_days()?.into_iter().collect();
+        let pixels = self.pixels(graph).await?;
+        let today = self.settings.today();
+        let (streak, goal_period) = if args.goal {
+            let goals = GoalsStruct::load()?;
+            let goal = goals.get(graph)
+                .ok_or_else(|| Error::MissingEntryInDatabase(format!("There is no goal set for {graph}. (habitcli goals set -h)")))?;
+            match goal.period {
+                GoalPeriod::Day => (cadence_streak(&pixels, today, &cadence, &rest_days, |pixel| pixel.quantity >= goal.target), None),
+                // weekly and monthly goals are counted in their own periods, the cadence doesn't apply
+                _ => (goal_streak(&pixels, today, &goal.period, goal.target, &rest_days), Some(goal.period.clone())),
+            }
+        } else {
+            (cadence_streak(&pixels, today, &cadence, &rest_days, |pixel| pixel.quantity != 0.0), None)
+        };
+        Ok(Output::Streak { graph: graph.to_string(), streak, cadence, goal_period })
     }
-    pub fn print_data(&self) -> Result<()> {
-        println!("{}", &self);
-        Ok(())
+
+    pub fn call_rest(&self, args: RestArgs<'_>) -> Result<Output> {
+        let user = user_data::User::new()?;
+        let parse = |from: &str, to: &str| -> Result<Vec<NaiveDate>> {
+            Ok(date_range(from, to)?.iter().map(|day| NaiveDate::parse_from_str(day, "%Y%m%d").expect("Formatted by date_range")).collect())
+        };
+        match args {
+            RestArgs::Add { from, to } => {
+                let days = parse(from, to)?;
+                user.add_rest_days(&days)?;
+                Ok(Output::message(format!("Added {} rest day(s).", days.len())))
+            }
+            RestArgs::Remove { from, to } => {
+                let removed = user.remove_rest_days(&parse(from, to)?)?;
+                Ok(Output::message(format!("Removed {removed} rest day(s).")))
+            }
+            RestArgs::List => {
+                let days = user.get_rest_days()?;
+                Ok(Output::RestDays { days: days.iter().map(|day| day.format("%Y%m%d").to_string()).collect() })
+            }
+        }
+    }
+
+    pub fn call_cadence(&self, args: CadenceArgs<'_>) -> Result<Output> {
+        let mut cadences = CadencesStruct::load()?;
+        match args {
+            CadenceArgs::Set { graph, cadence } => {
+                cadences.set(&resolve_graph(graph)?, cadence);
+                cadences.save()?;
+                Ok(Output::message("Cadence saved."))
+            }
+            CadenceArgs::Remove { graph } => {
+                let graph = &resolve_graph(graph)?;
+                if !cadences.remove(graph) {
+                    return Err(Error::MissingEntryInDatabase(format!("There is no cadence set for {graph}.")));
+                }
+                cadences.save()?;
+                Ok(Output::message(format!("Cadence removed, {graph} is a daily habit again.")))
+            }
+            CadenceArgs::List => Ok(Output::Cadences { cadences: cadences.cadences }),
+        }
+    }
+
+    pub async fn call_goals(&mut self, args: GoalArgs<'_>) -> Result<Output> {
+        let mut goals = GoalsStruct::load()?;
+        match args {
+            GoalArgs::Set { graph, target, period } => {
+                goals.set(Goal { graph: resolve_graph(graph)?, target, period });
+                goals.save()?;
+                Ok(Output::message("Goal saved."))
+            }
+            GoalArgs::Remove { graph } => {
+                let graph = &resolve_graph(graph)?;
+                if !goals.remove(graph) {
+                    return Err(Error::MissingEntryInDatabase(format!("There is no goal set for {graph}.")));
+                }
+                goals.save()?;
+                Ok(Output::message("Goal removed."))
+            }
+            GoalArgs::Show => {
+                if goals.goals.is_empty() {
+                    return Err(Error::MissingEntryInDatabase("No goals set. (habitcli goals set -h)".to_string()));
+                }
+                self.login()?;
+                let today = self.settings.today();
+                let mut progress = Vec::new();
+                for goal in goals.goals {
+                    let pixels = self.pixels(&goal.graph).await?;
+                    let total = period_total(&pixels, today, &goal.period);
+                    progress.push(GoalProgress {
+                        met: total >= goal.target,
+                        progress: total,
+                        today: period_total(&pixels, today, &GoalPeriod::Day),
+                        week: period_total(&pixels, today, &GoalPeriod::Week),
+                        month: period_total(&pixels, today, &GoalPeriod::Month),
+                        graph: goal.graph,
+                        target: goal.target,
+                        period: goal.period,
+                    });
+                }
+                Ok(Output::Goals { goals: progress })
+            }
+        }
+    }
+    pub async fn call_daemon(&self, once: bool, format: &OutputFormat) -> Result<Output> {
+        let reminders = RemindersStruct::load()?;
+        if once {
+            return Ok(Output::Reminder { missing: self.check_reminders(&reminders).await? });
+        }
+        loop {
+            let now = self.settings.now();
+            let next = reminders.next_after(now)?;
+            eprintln!("Next reminder check at {}.", self.settings.format_time(next));
+            tokio::time::sleep((next - now).to_std().unwrap_or_default()).await;
+            // one failed check shouldn't stop the daemon
+            match self.check_reminders(&reminders).await {
+                Ok(missing) => println!("{}", output::render(&Output::Reminder { missing }, format)),
+                Err(e) => eprintln!("There was an error while checking your graphs. {e}"),
+            }
+        }
+    }
+
+    async fn check_reminders(&self, reminders: &RemindersStruct) -> Result<Vec<String>> {
+        let name = &self.name.to_owned().expect("Data should be there");
+        let api_key = &self.api_key.to_owned().expect("Data should be there");
+        let graphs = if reminders.graphs.is_empty() {
+            self.graph_definitions().await?.into_iter().map(|graph| graph.id).collect()
+        } else {
+            reminders.graphs.clone()
+        };
+
+        let today = self.settings.today();
+        let mut missing = Vec::new();
+        for graph in graphs {
+            // float graphs count as done with any quantity too, like 0.5
+            let pixel = self.session.get_pixel(name, api_key, &graph, today).await?;
+            if pixel.is_none_or(|pixel| pixel.quantity == 0.0) {
+                missing.push(graph);
+            }
+        }
+        if !missing.is_empty() {
+            notification::remind(reminders, &missing)?;
+        }
+        Ok(missing)
+    }
+
+    pub async fn call_alias(&mut self, args: AliasArgs<'_>) -> Result<Output> {
+        let mut aliases = AliasesStruct::load()?;
+        match args {
+            AliasArgs::Add { alias, graph } => {
+                self.login()?;
+                let graphs: Vec<String> = self.live_graph_definitions().await?.into_iter().map(|graph| graph.id).collect();
+                if !graphs.iter().any(|id| id == graph) {
+                    return Err(Error::InvalidInput(format!("There is no graph {graph} on your account.")));
+                }
+                // an alias can't hide one of the graph ids
+                if graphs.iter().any(|id| id == alias) {
+                    return Err(Error::InvalidInput(format!("{alias} is already a graph id.")));
+                }
+                aliases.aliases.insert(alias.to_string(), graph.to_string());
+                aliases.save()?;
+                Ok(Output::message(format!("{alias} now stands for {graph}.")))
+            }
+            AliasArgs::Remove { alias } => {
+                if aliases.aliases.remove(alias).is_none() {
+                    return Err(Error::MissingEntryInDatabase(format!("There is no alias {alias}.")));
+                }
+                aliases.save()?;
+                Ok(Output::message("Alias removed."))
+            }
+            AliasArgs::List => Ok(Output::Aliases { aliases: graph_aliases(aliases) }),
+        }
+    }
+
+    pub async fn call_config(&mut self, args: ConfigArgs<'_>) -> Result<Output> {
+        match args {
+            ConfigArgs::List => Ok(Output::Settings { settings: settings_under(SettingsStruct::load()?, None)? }),
+            ConfigArgs::Get { key } => {
+                known_setting(key)?;
+                let settings = settings_under(SettingsStruct::load()?, Some(key))?;
+                match settings.as_slice() {
+                    [setting] if setting.key == key => Ok(Output::message(setting.value.clone())),
+                    [] => Ok(Output::message(format!("{key} is not set."))),
+                    _ => Ok(Output::Settings { settings }),
+                }
+            }
+            ConfigArgs::Set { key, value } => {
+                known_setting(key)?;
+                // checked against the account like with alias add
+                if let Some(alias) = key.strip_prefix("aliases.") {
+                    return self.call_alias(AliasArgs::Add { alias, graph: value }).await;
+                }
+                SettingsStruct::set(key, value)?;
+                Ok(Output::message(format!("{key} set to {value}.")))
+            }
+            ConfigArgs::Edit => {
+                // editors can't save into a directory that doesn't exist yet
+                std::fs::create_dir_all(user_data::config_dir()?)?;
+                let path = user_data::config_path()?;
+                let editor = std::env::var("VISUAL").or_else(|_| std::env::var("EDITOR"))
+                    .unwrap_or_else(|_| if cfg!(windows) { "notepad".to_string() } else { "vi".to_string() });
+                // editors are often given with flags, like "code --wait"
+                let mut parts = editor.split_whitespace();
+                let program = parts.next().ok_or_else(|| Error::InvalidInput("$EDITOR is empty.".to_string()))?;
+                let status = std::process::Command::new(program).args(parts).arg(&path).status()?;
+                if !status.success() {
+                    return Err(Error::InvalidInput(format!("{editor} exited with {status}, config.toml may not be saved.")));
+                }
+                // mistakes are reported right away instead of by the next command
+                SettingsStruct::load()?;
+                Ok(Output::message(format!("Saved {}.", path.display())))
+            }
+        }
+    }
+
+    pub async fn call_account(&self, args: AccountArgs<'_>) -> Result<Output> {
+        let name = self.name.as_deref().expect("Data should be there");
+        let api_key = self.api_key.as_deref().expect("Data should be there");
+        match args {
+            AccountArgs::Show => {
+                let profile = self.session.get_profile(name, api_key).await?;
+                Ok(Output::Profile { name: name.to_string(), url: self.session.profile_url(name), profile })
+            }
+            AccountArgs::Set(mut profile) => {
+                profile.pinned_graph_id = profile.pinned_graph_id.as_deref().map(resolve_graph).transpose()?;
+                self.session.update_profile(name, api_key, &profile).await?;
+                Ok(Output::message(format!("Profile updated, see it at {}.", self.session.profile_url(name))))
+            }
+            AccountArgs::Token { new_token } => {
+                validate_token(new_token)?;
+                self.session.update_token(name, api_key, new_token).await?;
+                // pixela already switched, losing the new token here would lock this device out
+                user_data::User::new()?.set_user_data(name, new_token).map_err(|err| Error::TroubleSavingLoginInfo(format!(
+                    "Pixela now uses your new token but saving it failed, log in again with `habitcli login {name} <new-token>`. {err}"
+                )))?;
+                Ok(Output::message("Token updated on Pixela and on this device."))
+            }
+            AccountArgs::Delete => {
+                eprintln!("This deletes the Pixela account {name} with all of its graphs and pixels, it can't be undone.");
+                if prompt("Type your username to confirm: ")? != name {
+                    return Err(Error::InvalidInput("The username didn't match, your account was not deleted.".to_string()));
+                }
+                self.session.delete_user(name, api_key).await?;
+                let user = user_data::User::new()?;
+                user.remove_user_data()?;
+                user.clear_cache()?;
+                Ok(Output::message(format!("Account {name} deleted, you are logged out.")))
+            }
+        }
+    }
+    pub async fn call_timer(&self, args: TimerArgs<'_>) -> Result<Output> {
+        // the database is opened for every access, the cache opens it too while a timer starts
+        let running = user_data::User::new()?.get_timer()?;
+        let timer_output = |timer: &Timer| Output::Timer {
+            graph: timer.graph.clone(),
+            started: self.settings.format_time(self.settings.local(timer.started_at)),
+            elapsed_seconds: (chrono::Utc::now() - timer.started_at).num_seconds(),
+        };
+        match args {
+            TimerArgs::Start { graph } => {
+                if let Some(timer) = running {
+                    return Err(Error::InvalidInput(format!("A timer for {} is already running, stop or cancel it first.", timer.graph)));
+                }
+                let graph = resolve_graph(graph)?;
+                let definition = self.graph_definition(&graph).await?;
+                // checked now so that the time isn't lost on stop
+                if elapsed_in_unit(TimeDelta::zero(), &definition.unit, &definition.number_type).is_none() {
+                    return Err(Error::InvalidInput(format!("{graph} is counted in {}, timers only work with seconds, minutes or hours.", definition.unit)));
+                }
+                let timer = Timer { graph, started_at: chrono::Utc::now() };
+                user_data::User::new()?.set_timer(&timer)?;
+                Ok(timer_output(&timer))
+            }
+            TimerArgs::Status => match running {
+                Some(timer) => Ok(timer_output(&timer)),
+                None => Ok(Output::message("No timer is running.")),
+            },
+            TimerArgs::Cancel => match running {
+                Some(timer) => {
+                    user_data::User::new()?.remove_timer()?;
+                    Ok(Output::message(format!("Timer for {} cancelled after {}.", timer.graph, output::format_elapsed((chrono::Utc::now() - timer.started_at).num_seconds()))))
+                }
+                None => Err(Error::MissingEntryInDatabase("No timer is running.".to_string())),
+            },
+            TimerArgs::Stop => {
+                let Some(timer) = running else {
+                    return Err(Error::MissingEntryInDatabase("No timer is running. (habitcli timer start -h)".to_string()));
+                };
+                let elapsed = chrono::Utc::now() - timer.started_at;
+                let definition = self.graph_definition(&timer.graph).await?;
+                let quantity = elapsed_in_unit(elapsed, &definition.unit, &definition.number_type)
+                    .ok_or_else(|| Error::InvalidInput(format!("{} isn't counted in seconds, minutes or hours anymore.", timer.graph)))?;
+                if quantity.parse::<f64>().is_ok_and(|quantity| quantity == 0.0) {
+                    user_data::User::new()?.remove_timer()?;
+                    return Ok(Output::message(format!("Timer stopped after {}, less than one {} so nothing was sent.", output::format_elapsed(elapsed.num_seconds()), definition.unit)));
+                }
+                let message = self.add_to_today(&timer.graph, &quantity).await?;
+                // the timer is only forgotten once the time is on pixela
+                user_data::User::new()?.remove_timer()?;
+                let date = self.settings.today().format("%Y%m%d").to_string();
+                let (summed, sum_error) = match self.refresh_dependent_sums(std::slice::from_ref(&timer.graph), &date).await {
+                    Ok(summed) => (summed, None),
+                    Err(e) => (Vec::new(), Some(e.to_string())),
+                };
+                forget_pixels(summed.iter().map(String::as_str).chain([timer.graph.as_str()]));
+                Ok(Output::Sent { graph: timer.graph, date, quantity, message, summed, sum_error })
+            }
+        }
+    }
+    async fn add_to_today(&self, graph: &str, quantity: &str) -> Result<String> {
+        let name = self.name.as_deref().expect("Data should be there");
+        let api_key = self.api_key.as_deref().expect("Data should be there");
+        let url = self.session.graph_url(name, graph);
+        match self.session.add_quantity(&url, quantity, api_key).await {
+            Ok(()) => return Ok(format!("Added {quantity} to today's pixel.")),
+            // some accounts can't use the add endpoint, the pixel is read and sent again instead
+            Err(Error::PixelaError(_)) => {}
+            Err(err) => return Err(err),
+        }
+        let today = self.settings.today();
+        let current = self.session.get_pixels(name, api_key, graph).await?
+            .into_iter()
+            .find(|pixel| pixel.date == today);
+        let total = current.as_ref().map_or(0.0, |pixel| pixel.quantity) + quantity.parse::<f64>().expect("Quantities of timers are numbers");
+        // sending replaces the pixel, its note is sent again with it
+        let optional_data = current.and_then(|pixel| pixel.optional_data);
+        send_checked(&self.session, &url, &total.to_string(), &today.format("%Y%m%d").to_string(), optional_data.as_deref(), api_key).await
+    }
+    async fn graph_definition(&self, graph: &str) -> Result<GraphDefinition> {
+        self.live_graph_definitions().await?.into_iter()
+            .find(|definition| definition.id == graph)
+            .ok_or_else(|| Error::InvalidInput(format!("There is no graph {graph} on your account.")))
+    }
+
+    // answers from the cache while it is fresh, a stale cache is still used when pixela can't be reached
+    async fn cached<T: Serialize + DeserializeOwned>(&self, key: &str, ttl: TimeDelta, fetch: impl Future<Output = Result<T>>) -> Result<T> {
+        let cached = match user_data::User::new()?.get_cached::<T>(key)? {
+            Some(cached) if !self.refresh && cached.is_fresh(ttl) => return Ok(cached.value),
+            cached => cached,
+        };
+        match fetch.await {
+            Ok(value) => {
+                // a failure to cache shouldn't fail the command
+                let _ = user_data::User::new().and_then(|user| user.set_cached(key, &value));
+                Ok(value)
+            }
+            Err(Error::ReqwestError(err)) if !err.is_decode() => match cached {
+                Some(cached) => {
+                    eprintln!("Warning: Couldn't reach Pixela, showing data saved {} ago. ({err})", describe_age(cached.age()));
+                    Ok(cached.value)
+                }
+                None => Err(Error::ReqwestError(err)),
+            },
+            Err(err) => Err(err),
+        }
+    }
+
+    async fn graph_definitions(&self) -> Result<Vec<GraphDefinition>> {
+        let name = &self.name.to_owned().expect("Data should be there");
+        let api_key = &self.api_key.to_owned().expect("Data should be there");
+        let url = self.session.graphs_url(name);
+        self.cached(user_data::GRAPHS_KEY, user_data::GRAPHS_TTL, self.session.get_graph_definitions(api_key, &url)).await
+    }
+
+    // graphs straight from pixela for commands that check or change something, the cache is refreshed on the way
+    async fn live_graph_definitions(&self) -> Result<Vec<GraphDefinition>> {
+        let name = &self.name.to_owned().expect("Data should be there");
+        let api_key = &self.api_key.to_owned().expect("Data should be there");
+        let graphs = self.session.get_graph_definitions(api_key, &self.session.graphs_url(name)).await?;
+        let _ = user_data::User::new().and_then(|user| user.set_cached(user_data::GRAPHS_KEY, &graphs));
+        Ok(graphs)
+    }
+
+    async fn pixels(&self, graph: &str) -> Result<Vec<Pixel>> {
+        let name = &self.name.to_owned().expect("Data should be there");
+        let api_key = &self.api_key.to_owned().expect("Data should be there");
+        self.cached(&user_data::pixels_key(graph), user_data::PIXELS_TTL, self.session.get_pixels(name, api_key, graph)).await
+    }
+
+    // every pixel from the given day, or back until a year without any pixel
+    async fn history(&self, graph: &str, from: Option<NaiveDate>) -> Result<Vec<Pixel>> {
+        let name = &self.name.to_owned().expect("Data should be there");
+        let api_key = &self.api_key.to_owned().expect("Data should be there");
+        let mut to = self.settings.today();
+        if from.is_some_and(|from| from > to) {
+            return Err(Error::InvalidInput("The start of the history can't be in the future.".to_string()));
+        }
+        let mut pixels = Vec::new();
+        loop {
+            let start = from.map_or(to - Days::new(364), |from| from.max(to - Days::new(364)));
+            let year = self.session.get_pixels_between(name, api_key, graph, start, to).await?;
+            let done = match from {
+                Some(from) => start == from,
+                None => year.is_empty(),
+            };
+            pixels.extend(year);
+            if done {
+                return Ok(pixels);
+            }
+            to = start - Days::new(1);
+        }
+    }
+
+    pub fn call_data(&self) -> Result<Output> {
+        let name = self.name.clone().expect("Data should be there");
+        Ok(Output::UserData { name, sum_graphs: SumGraphsStruct::load().ok() })
     }
 
 }
-fn input_graph_names(sum_graphs: &mut Vec<SumGraphStruct>, sum_graph_names_duplicate_tracker: &mut Vec<String>, sum_graphs_amount: usize, correct_names: &Vec<String>) -> Result<()> {
+fn input_graph_names(sum_graphs: &mut Vec<SumGraphStruct>, sum_graph_names_duplicate_tracker: &mut Vec<String>, sum_graphs_amount: usize, correct_names: &Vec<String>, aliases: &AliasesStruct) -> Result<()> {
     let mut correct_names_string = String::new(); 
     correct_names.iter().for_each(|name| correct_names_string.push_str(&format!("{name}\n")));
-    println!("Graphs available for your account:\n{correct_names_string}");
+    // prompts go to stderr so stdout only carries the command's output
+    eprintln!("Graphs available for your account:\n{correct_names_string}");
     while sum_graphs.len() < sum_graphs_amount {
         let mut input: String = String::new();
-        println!("Enter data #{}: (sum_graph_id graph_id(s)...", sum_graphs.len()+1);
-        stdin().read_line(&mut input).unwrap();
-        let input: Vec<_> = input.trim().split(" ").collect();
+        eprintln!("Enter data #{}: (sum_graph_id graph_id(s)...", sum_graphs.len()+1);
+        stdin().read_line(&mut input)?;
+        let input: Vec<_> = input.trim().split(" ").map(|graph| aliases.resolve(graph)).collect();
         let sum_graph_name = input.get(0).unwrap().to_string();
 
         // check for duplicates
@@ -240,23 +928,263 @@ fn input_graph_names(sum_graphs: &mut Vec<SumGraphStruct>, sum_graph_names_dupli
         graphs.iter().try_for_each(|name| -> Result<()> {if !correct_names.contains(name) {return Err(Error::SumGraphError(SumGraphError::new(SumGraphErrorKind::IncorrectNames)))}; return Ok(())})?;
         graphs.remove(0);
         sum_graphs.push(SumGraphStruct::new(sum_graph_name, graphs));
-        println!("Input accepted.");
+        eprintln!("Input accepted.");
     }
         Ok(())
 
 }
+// sums every sum graph for a single day, returns the names of sum graphs that were sent
+async fn sum_graphs_for_date(session: &Session, name: &str, api_key: &str, sum_graphs: &[SumGraphStruct], date: &str, skip_unchanged: bool) -> Result<Vec<String>> {
+    let mut updated = Vec::new();
+    for graph in sum_graphs {
+        let commits = Arc::new(Mutex::new(0));
+        let mut tasks: Vec<JoinHandle<Result<()>>> = Vec::new();
+        for graph_name in &graph.graphs_to_sum {
+            let session = session.clone();
+            let url = session.graph_url(name, graph_name);
+            let date = date.to_string();
+            let api_key = api_key.to_string();
+            let commits = Arc::clone(&commits);
+            tasks.push(tokio::spawn(async move {
+                session.async_get_graph_val(&url, &date, &api_key, commits).await
+            }));
+        }
+        for task in tasks {
+            task.await.expect("Pixel fetching task panicked")?;
+        }
+        let sum = *commits.lock().await;
+
+        let url = session.graph_url(name, &graph.sum_graph_name);
+        if skip_unchanged {
+            let remote = Arc::new(Mutex::new(0));
+            session.async_get_graph_val(&url, date, api_key, Arc::clone(&remote)).await?;
+            if *remote.lock().await == sum {
+                continue;
+            }
+        }
+        session.send_pixel(&url, &sum.to_string(), Some(date), None, api_key).await?;
+        updated.push(graph.sum_graph_name.clone());
+    }
+    Ok(updated)
+}
+
+// graph ids written as aliases are swapped for the graph id
+fn resolve_graph(graph: &str) -> Result<String> {
+    Ok(AliasesStruct::load()?.resolve(graph).to_string())
+}
+
+// top level names of config.toml
+fn known_setting(key: &str) -> Result<()> {
+    if !SettingsStruct::keys().iter().any(|setting| key.split('.').next() == Some(setting)) {
+        return Err(Error::InvalidInput(format!("There is no setting {key}, `habitcli config list` shows them all.")));
+    }
+    Ok(())
+}
+
+fn settings_under(settings: SettingsStruct, key: Option<&str>) -> Result<Vec<Setting>> {
+    // a key picks itself or a whole section, like "retry" or "aliases"
+    Ok(settings.entries()?.into_iter()
+        .filter(|(name, _)| key.is_none_or(|key| name == key || name.starts_with(&format!("{key}."))))
+        .map(|(key, value)| Setting { key, value })
+        .collect())
+}
+
+fn graph_aliases(aliases: AliasesStruct) -> Vec<GraphAlias> {
+    aliases.aliases.into_iter().map(|(alias, graph)| GraphAlias { alias, graph }).collect()
+}
+
+fn update_cached_graphs(update: impl FnOnce(&mut Vec<GraphDefinition>)) {
+    // keeps the cache in sync with graphs created or removed from habitcli
+    let Ok(user) = user_data::User::new() else { return };
+    if let Ok(Some(mut graphs)) = user.get_cached::<Vec<GraphDefinition>>(user_data::GRAPHS_KEY) {
+        update(&mut graphs.value);
+        let _ = user.set_cached(user_data::GRAPHS_KEY, &graphs.value);
+    }
+}
+
+fn forget_pixels<'a>(graphs: impl IntoIterator<Item = &'a str>) {
+    // cached pixels of written graphs are outdated, the next read fetches them again
+    let keys: Vec<String> = graphs.into_iter().map(user_data::pixels_key).collect();
+    let _ = user_data::User::new().and_then(|user| user.remove_cached(&keys));
+}
+
+fn describe_age(age: TimeDelta) -> String {
+    match (age.num_days(), age.num_hours(), age.num_minutes()) {
+        (days, _, _) if days > 0 => format!("{days} day(s)"),
+        (_, hours, _) if hours > 0 => format!("{hours} hour(s)"),
+        (_, _, minutes) => format!("{minutes} minute(s)"),
+    }
+}
+
+// asks on stderr and reads one trimmed line, the end of input is an empty answer
+fn prompt(question: &str) -> Result<String> {
+    eprint!("{question}");
+    let mut input = String::new();
+    stdin().read_line(&mut input)?;
+    Ok(input.trim().to_string())
+}
+
+fn confirm(question: &str) -> Result<bool> {
+    Ok(matches!(prompt(&format!("{question} [y/N] "))?.to_lowercase().as_str(), "y" | "yes"))
+}
+
+// sends a pixel and turns a rejection into an error, returns pixela's message
+async fn send_checked(session: &Session, url: &str, quantity: &str, date: &str, optional_data: Option<&str>, api_key: &str) -> Result<String> {
+    let msg = match session.send_pixel(url, quantity, Some(date), optional_data, api_key).await? {
+        CallResult::ApiResponse(msg) => msg,
+        _ => return Err(Error::PixelaError("Unexpected response to a sent pixel".to_string())),
+    };
+    if !msg.is_success() {
+        return Err(Error::PixelaError(msg.text()));
+    }
+    Ok(msg.text())
+}
+
+// --note and --data merged into pixela's optionalData, a json object of at most 10KB
+fn optional_data(note: Option<&str>, data: Option<&str>) -> Result<Option<String>> {
+    let mut fields = match data.map(serde_json::from_str::<serde_json::Value>) {
+        None => serde_json::Map::new(),
+        Some(Ok(serde_json::Value::Object(fields))) => fields,
+        Some(_) => return Err(Error::InvalidInput("--data has to be a json object, ex. '{\"mood\": 3}'.".to_string())),
+    };
+    if let Some(note) = note {
+        fields.insert("note".to_string(), serde_json::Value::String(note.to_string()));
+    }
+    if note.is_none() && data.is_none() {
+        return Ok(None);
+    }
+    let optional_data = serde_json::Value::Object(fields).to_string();
+    if optional_data.len() > 10 * 1024 {
+        return Err(Error::InvalidInput("Notes and data of a pixel can't be longer than 10KB.".to_string()));
+    }
+    Ok(Some(optional_data))
+}
+
+// elapsed time in a graph's unit, None when the unit isn't a time unit
+fn elapsed_in_unit(elapsed: TimeDelta, unit: &str, number_type: &str) -> Option<String> {
+    let seconds_per_unit = match unit.trim().to_lowercase().as_str() {
+        "s" | "sec" | "secs" | "second" | "seconds" => 1.0,
+        "m" | "min" | "mins" | "minute" | "minutes" => 60.0,
+        "h" | "hr" | "hrs" | "hour" | "hours" => 3600.0,
+        _ => return None,
+    };
+    // only whole units that have passed count, 31 seconds aren't a minute yet
+    let quantity = elapsed.num_seconds() as f64 / seconds_per_unit;
+    match number_type {
+        "int" => Some((quantity.floor() as i64).to_string()),
+        _ => Some(format!("{:.2}", (quantity * 100.0).floor() / 100.0)),
+    }
+}
+
+// one answer of the checkin wizard, None skips the graph
+fn checkin_answer(input: &str, default: Option<&str>, number_type: &str) -> std::result::Result<Option<String>, String> {
+    let quantity = match input {
+        "" => return Ok(default.map(str::to_string)),
+        "s" | "skip" => return Ok(None),
+        quantity => quantity,
+    };
+    let valid = match number_type {
+        "int" => quantity.parse::<i64>().is_ok(),
+        _ => quantity.parse::<f64>().is_ok_and(f64::is_finite),
+    };
+    match valid {
+        true => Ok(Some(quantity.to_string())),
+        false => Err(format!("{quantity} isn't a valid {number_type} quantity, try again or type \"s\" to skip.")),
+    }
+}
+
+// every day between from and to (inclusive) in pixela's "yyyymmdd" format
+fn date_range(from: &str, to: &str) -> Result<Vec<String>> {
+    let invalid = || Error::SumGraphError(SumGraphError::new(SumGraphErrorKind::InvalidDateRange));
+    let from = NaiveDate::parse_from_str(from, "%Y%m%d").map_err(|_| invalid())?;
+    let to = NaiveDate::parse_from_str(to, "%Y%m%d").map_err(|_| invalid())?;
+    if from > to {
+        return Err(invalid());
+    }
+    Ok(from.iter_days()
+        .take_while(|day| *day <= to)
+        .map(|day| day.format("%Y%m%d").to_string())
+        .collect())
+}
 #[cfg(test)]
 mod test {
-    use crate::{args::{SumArgs, SumGraphArgs}, pixela::Session, Worker};
+    use clap::Parser;
+    use crate::{args::{CommandType, HabitCLIArgs, IntoArguments, ParsedArguments, SumArgs, SumGraphArgs}, checkin_answer, date_range, elapsed_in_unit, optional_data, pixela::Session, Worker};
 
     #[tokio::test]
     async fn saving_graphs() {
         let session = Session::new();
         let worker = Worker::new(session);
-        let args = SumGraphArgs{sum_graph_amount: 2};
+        let args = SumGraphArgs{sum_graph_amount: 2, auto_sum: false};
         worker.setup_graphs(args).await.unwrap();
 
     }
+    #[test]
+    fn batch_send_args() {
+        let single = HabitCLIArgs::parse_from(["habitcli", "send", "rd1", "30"]);
+        let CommandType::Send(single) = single.command_type else { panic!("Expected send") };
+        assert!(matches!(single.into_args(), ParsedArguments::PixelArgs(args) if args.graph == "rd1" && args.quantity == "30"));
+
+        let batch = HabitCLIArgs::parse_from(["habitcli", "send", "-d", "20240301", "reading=30", "gym=1", "water=8"]);
+        let CommandType::Send(batch) = batch.command_type else { panic!("Expected send") };
+        match batch.into_args() {
+            ParsedArguments::BatchArgs(args) => {
+                assert_eq!(args.date, Some("20240301"));
+                assert_eq!(args.pixels, vec!["reading=30", "gym=1", "water=8"]);
+            }
+            _ => panic!("Expected a batch"),
+        }
+    }
+    #[test]
+    fn signup_args() {
+        let signup = HabitCLIArgs::parse_from(["habitcli", "signup", "tester", "secret-token", "--agree-tos"]);
+        let CommandType::Signup(signup) = signup.command_type else { panic!("Expected signup") };
+        match signup.into_args() {
+            ParsedArguments::NewUserData(args) => {
+                assert_eq!((args.username, args.token), ("tester", Some("secret-token")));
+                assert!(args.agree_tos && !args.not_minor);
+            }
+            _ => panic!("Expected new user data"),
+        }
+    }
+    #[test]
+    fn sum_date_range() {
+        assert_eq!(date_range("20240227", "20240302").unwrap(), vec!["20240227", "20240228", "20240229", "20240301", "20240302"]);
+        assert_eq!(date_range("20240101", "20240101").unwrap(), vec!["20240101"]);
+        assert!(date_range("20240102", "20240101").is_err());
+        assert!(date_range("2024-01-01", "20240102").is_err());
+    }
+    #[test]
+    fn pixel_optional_data() {
+        assert_eq!(optional_data(None, None).unwrap(), None);
+        assert_eq!(optional_data(Some("tired"), None).unwrap().as_deref(), Some(r#"{"note":"tired"}"#));
+        let merged: serde_json::Value = serde_json::from_str(&optional_data(Some("tired"), Some(r#"{"mood": 2}"#)).unwrap().unwrap()).unwrap();
+        assert_eq!(merged, serde_json::json!({"mood": 2, "note": "tired"}));
+        assert!(optional_data(None, Some("[1, 2]")).is_err());
+        assert!(optional_data(Some(&"x".repeat(11 * 1024)), None).is_err());
+    }
+    #[test]
+    fn timer_units() {
+        let elapsed = chrono::TimeDelta::seconds(95 * 60);
+        assert_eq!(elapsed_in_unit(elapsed, "minutes", "int").as_deref(), Some("95"));
+        assert_eq!(elapsed_in_unit(elapsed, "Hours", "float").as_deref(), Some("1.58"));
+        assert_eq!(elapsed_in_unit(elapsed, "h", "int").as_deref(), Some("1"));
+        assert_eq!(elapsed_in_unit(elapsed, "pages", "int"), None);
+        assert_eq!(elapsed_in_unit(chrono::TimeDelta::seconds(31), "minutes", "int").as_deref(), Some("0"));
+        assert_eq!(elapsed_in_unit(chrono::TimeDelta::seconds(119), "minutes", "int").as_deref(), Some("1"));
+        assert_eq!(elapsed_in_unit(chrono::TimeDelta::seconds(120), "minutes", "int").as_deref(), Some("2"));
+        assert_eq!(elapsed_in_unit(chrono::TimeDelta::seconds(3599), "hours", "float").as_deref(), Some("0.99"));
+    }
+    #[test]
+    fn checkin_answers() {
+        assert_eq!(checkin_answer("", Some("30"), "int"), Ok(Some("30".to_string())));
+        assert_eq!(checkin_answer("", None, "int"), Ok(None));
+        assert_eq!(checkin_answer("s", Some("30"), "int"), Ok(None));
+        assert_eq!(checkin_answer("12", Some("30"), "int"), Ok(Some("12".to_string())));
+        assert_eq!(checkin_answer("1.5", None, "float"), Ok(Some("1.5".to_string())));
+        assert!(checkin_answer("1.5", None, "int").is_err());
+        assert!(checkin_answer("lots", None, "float").is_err());
+    }
 
 
 }
@@ -1,91 +1,186 @@
+use std::{process::ExitCode, time::Duration};
+
 use clap::Parser;
 use habitcli::{
-    args::{self, IntoArguments, ParsedArguments},
-    pixela::Session, Worker, error::Error
+    args::{self, CommandType, IntoArguments, ParsedArguments},
+    output::{self, Output, OutputFormat},
+    pixela::Session, Worker, completions, tui, error::{self, Error}, user_data::SettingsStruct
 };
 #[tokio::main]
-async fn main() -> Result<(), Error> {
+async fn main() -> ExitCode {
     let args = args::HabitCLIArgs::parse();
+    if let Some(dir) = args.config_dir {
+        habitcli::user_data::set_home(dir);
+    }
+    // a broken config.toml is reported by the command itself, the output format falls back to plain
+    let format = args.output.or_else(|| SettingsStruct::load().ok().map(|settings| settings.output)).unwrap_or_default();
+
+    // errors go to stderr, exit codes are documented in error.rs
+    let session = Session::new().with_timeout(Duration::from_secs(args.timeout));
+    match run(args.command_type, Worker::new(session).with_refresh(args.refresh), &format).await {
+        Ok(output) => {
+            println!("{}", output::render(&output, &format));
+            if output.failed() { ExitCode::from(error::EXIT_PARTIAL_FAILURE) } else { ExitCode::SUCCESS }
+        }
+        Err(e) => {
+            eprintln!("{}", output::render_error(&e, &format));
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
 
-    let session = Session::new();
-    let mut worker = Worker::new(session);
-// match statement for every possible user inputted command
-    match args.command_type {
-        args::CommandType::Signup(arguments) => {
-            if let ParsedArguments::NewUserData(args) = arguments.into_args() {
-                worker.call_create_user(args).await?;
+async fn run(command: CommandType, mut worker: Worker, format: &OutputFormat) -> Result<Output, Error> {
+// match statement for every possible user inputted command, every command results in an output
+    let output: Output = match command {
+        CommandType::Signup(arguments) => {
+            worker.load_settings()?;
+            match arguments.into_args() {
+                ParsedArguments::NewUserData(args) => worker.call_create_user(args).await?,
+                _ => unreachable!(),
             }
         }
-        args::CommandType::Send(arguments) => {
+        CommandType::Send(arguments) => {
             worker.login()?;
-            if let ParsedArguments::PixelArgs(args) = arguments.into_args() {
-                worker.call_send(args).await;
+            match arguments.into_args() {
+                ParsedArguments::PixelArgs(args) => worker.call_send(args).await?,
+                ParsedArguments::BatchArgs(args) => worker.call_send_batch(args).await?,
+                _ => unreachable!(),
             }
         }
+        CommandType::Log(arguments) => {
+            worker.login()?;
+            worker.call_log(arguments.date.as_deref()).await?
+        }
+        CommandType::Checkin(arguments) => {
+            worker.login()?;
+            worker.call_checkin(arguments.date.as_deref(), &arguments.graphs).await?
+        }
 
-        args::CommandType::Get(arguments) => {
+        CommandType::Get(arguments) => {
             worker.login()?;
-            if let ParsedArguments::PixelArgs(args) = arguments.into_args() {
-                worker.call_get(args).await;
+            match arguments.into_args() {
+                ParsedArguments::PixelArgs(args) => worker.call_get(args).await?,
+                _ => unreachable!(),
             }
         }
 
-        args::CommandType::Login(arguments) => {
-            if let ParsedArguments::LoginArgs(args) = arguments.into_args() {
-                match worker.call_save_data(args) {
-                    Ok(_) => (),
-                    Err(e) => println!("{:?}", e),
-                }
+        CommandType::Notes(arguments) => {
+            worker.login()?;
+            worker.call_notes(&arguments.graph_id, arguments.search.as_deref(), arguments.from.as_deref()).await?
+        }
+        CommandType::Login(arguments) => {
+            worker.load_settings()?;
+            match arguments.into_args() {
+                ParsedArguments::LoginArgs(args) => worker.call_save_data(args).await?,
+                _ => unreachable!(),
+            }
+        }
+        CommandType::Logout(arguments) => worker.call_logout(arguments.yes)?,
+        CommandType::Reset(arguments) => worker.call_reset(arguments.yes)?,
+        CommandType::Account(arguments) => {
+            worker.login()?;
+            match arguments.into_args() {
+                ParsedArguments::AccountArgs(args) => worker.call_account(args).await?,
+                _ => unreachable!(),
             }
         }
-        args::CommandType::Create(arguments) => {
+        CommandType::Create(arguments) => {
             worker.login()?;
-            if let ParsedArguments::GraphCreateArgs(args) = arguments.into_args() {
-                worker.call_create_graph(args).await?;
+            match arguments.into_args() {
+                ParsedArguments::GraphCreateArgs(args) => worker.call_create_graph(args).await?,
+                _ => unreachable!(),
             }
         }
-        args::CommandType::Remove(arguments) => {
+        CommandType::Remove(arguments) => {
             worker.login()?;
-            if let ParsedArguments::RemoveArgs(args) = arguments.into_args() {
-                worker.call_remove_graph(args).await?;
+            match arguments.into_args() {
+                ParsedArguments::RemoveArgs(args) => worker.call_remove_graph(args).await?,
+                _ => unreachable!(),
             }
         }
-        args::CommandType::List(_) => {
+        CommandType::Tui(_) => {
+            worker.login()?;
+            tui::run(&worker).await?
+        }
+        CommandType::List(_) => {
+            worker.login()?;
+            worker.call_list().await?
+        }
+        CommandType::Streak(arguments) => {
             worker.login()?;
-            worker.call_list().await?;
+            match arguments.into_args() {
+                ParsedArguments::StreakGetArgs(args) => worker.call_streak(args).await?,
+                _ => unreachable!(),
+            }
         }
-        args::CommandType::Streak(arguments) => {
+        CommandType::SetupSum(arguments) => {
             worker.login()?;
-            if let ParsedArguments::StreakGetArgs(args) = arguments.into_args() {
-                worker.call_streak(args).await?
+            match arguments.into_args() {
+                ParsedArguments::SumGraphArgs(args) => worker.setup_graphs(args).await?,
+                ParsedArguments::SumGraphCheck => worker.check_sum_graphs().await?,
+                _ => unreachable!(),
             }
         }
-        args::CommandType::SetupSum(arguments) => {
+        CommandType::Goals(arguments) => {
+            match arguments.into_args() {
+                ParsedArguments::GoalArgs(args) => worker.call_goals(args).await?,
+                _ => unreachable!(),
+            }
+        }
+        CommandType::Cadence(arguments) => {
+            match arguments.into_args() {
+                ParsedArguments::CadenceArgs(args) => worker.call_cadence(args)?,
+                _ => unreachable!(),
+            }
+        }
+        CommandType::Alias(arguments) => {
+            match arguments.into_args() {
+                ParsedArguments::AliasArgs(args) => worker.call_alias(args).await?,
+                _ => unreachable!(),
+            }
+        }
+        CommandType::Config(arguments) => {
+            match arguments.into_args() {
+                ParsedArguments::ConfigArgs(args) => worker.call_config(args).await?,
+                _ => unreachable!(),
+            }
+        }
+        CommandType::Timer(arguments) => {
             worker.login()?;
-            if let ParsedArguments::SumGraphArgs(args) = arguments.into_args()  {
-                match worker.setup_graphs(args).await{
-                    Ok(_) => (),
-                    Err(e) => println!("{e}")
-                }
+            match arguments.into_args() {
+                ParsedArguments::TimerArgs(args) => worker.call_timer(args).await?,
+                _ => unreachable!(),
+            }
+        }
+        CommandType::Rest(arguments) => {
+            match arguments.into_args() {
+                ParsedArguments::RestArgs(args) => worker.call_rest(args)?,
+                _ => unreachable!(),
             }
         }
-        args::CommandType::Data(_) => {
+        CommandType::Daemon(arguments) => {
             worker.login()?;
-            worker.print_data()?;
+            worker.call_daemon(arguments.once, format).await?
         }
-
-        args::CommandType::Sum(arguments) => {
+        CommandType::Completions(arguments) => Output::message(completions::script(arguments.shell)),
+        CommandType::CompleteGraphs(arguments) => Output::message(completions::graph_ids(arguments.sum).join("\n")),
+        CommandType::Whoami(_) => {
             worker.login()?;
-            if let ParsedArguments::SumArgs(args) = arguments.into_args() {
-                match worker.handle_sum_graph(args).await {
-                    Ok(_) => (),
-                    Err(e) => println!("{:?}",e)
-                }
+            worker.call_whoami().await?
+        }
+        CommandType::Data(_) => {
             worker.login()?;
-    
+            worker.call_data()?
         }
+
+        CommandType::Sum(arguments) => {
+            worker.login()?;
+            match arguments.into_args() {
+                ParsedArguments::SumArgs(args) => worker.handle_sum_graph(args).await?,
+                _ => unreachable!(),
+            }
         }
 
     };
-    Ok(())
+    Ok(output)
 }
@@ -0,0 +1,80 @@
+/*
+ Reminders sent to the user when a habit is still missing
+ */
+use std::process::Command;
+
+use crate::{error::{Error, Result}, user_data::{ReminderMethod, RemindersStruct}};
+
+// reminders go to stderr like prompts, stdout only carries the output of the daemon
+pub fn remind(reminders: &RemindersStruct, missing_graphs: &[String]) -> Result<()> {
+    let body = format!("No pixel for today in: {}", missing_graphs.join(", "));
+    // every method is tried, one failing shouldn't silence the others
+    let mut failures = Vec::new();
+    for method in &reminders.methods {
+        let result = match method {
+            ReminderMethod::Desktop => desktop_notification(&body),
+            ReminderMethod::Bell => {
+                eprintln!("\x07{body}");
+                Ok(())
+            }
+            ReminderMethod::Hook => run_hook(reminders.hook.as_deref(), missing_graphs),
+        };
+        if let Err(err) = result {
+            failures.push(err);
+        }
+    }
+    match failures.is_empty() {
+        true => Ok(()),
+        false => Err(Error::NotificationError(failures)),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn desktop_notification(body: &str) -> std::result::Result<(), String> {
+    // goes through the freedesktop notification service on D-Bus
+    notify_rust::Notification::new()
+        .summary("habitcli")
+        .body(body)
+        .show()
+        .map_err(|err| format!("Couldn't show a desktop notification: {err}."))?;
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn desktop_notification(body: &str) -> std::result::Result<(), String> {
+    eprintln!("{body}");
+    Ok(())
+}
+
+fn run_hook(hook: Option<&str>, missing_graphs: &[String]) -> std::result::Result<(), String> {
+    let hook = hook.ok_or("Hook reminders need a hook command in the notifications of config.toml.")?;
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(hook)
+        .env("HABITCLI_MISSING_GRAPHS", missing_graphs.join(","))
+        .status()
+        .map_err(|err| format!("Couldn't run the reminder hook: {err}."))?;
+    if !status.success() {
+        return Err(format!("Reminder hook exited with {status}."));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_method_is_tried() {
+        let missing = vec!["rd1".to_string()];
+        let reminders = |hook: Option<&str>| RemindersStruct { times: vec![], graphs: vec![], methods: vec![ReminderMethod::Hook, ReminderMethod::Bell, ReminderMethod::Hook], hook: hook.map(str::to_string) };
+        match remind(&reminders(None), &missing) {
+            Err(Error::NotificationError(failures)) => assert_eq!(failures.len(), 2),
+            _ => panic!("Expected both hooks to fail"),
+        }
+        let err = remind(&reminders(Some("exit 3")), &missing).err().unwrap();
+        assert!(err.to_string().contains("exit status: 3"));
+        assert_eq!(err.exit_code(), crate::error::EXIT_OTHER);
+        assert!(remind(&reminders(Some("test \"$HABITCLI_MISSING_GRAPHS\" = rd1")), &missing).is_ok());
+    }
+}
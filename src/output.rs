@@ -0,0 +1,377 @@
+/*
+ Results of every command and the single place where they are rendered
+ for the user (plain prose, tables) or for scripts (json)
+ */
+use serde::{Deserialize, Serialize};
+
+use crate::error::Error;
+use crate::pixela::{prepare_cadence_streak_string, Profile};
+use crate::user_data::{Cadence, GoalPeriod, GraphCadence, SumGraphsStruct};
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Plain,
+    Table,
+    Json,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Output {
+    Message {
+        message: String,
+    },
+    Sent {
+        graph: String,
+        date: String,
+        quantity: String,
+        message: String,
+        // sum graphs updated automatically after the send
+        summed: Vec<String>,
+        sum_error: Option<String>,
+    },
+    Batch {
+        date: String,
+        pixels: Vec<SentPixel>,
+        summed: Vec<String>,
+        sum_error: Option<String>,
+    },
+    Pixel {
+        graph: String,
+        date: String,
+        quantity: f64,
+        note: Option<String>,
+    },
+    Notes {
+        graph: String,
+        notes: Vec<PixelNote>,
+    },
+    Graphs {
+        graphs: Vec<String>,
+        aliases: Vec<GraphAlias>,
+    },
+    Streak {
+        graph: String,
+        streak: u32,
+        cadence: Cadence,
+        // set when the streak counts weeks or months of a --goal instead of the cadence
+        #[serde(skip_serializing_if = "Option::is_none")]
+        goal_period: Option<GoalPeriod>,
+    },
+    Sums {
+        days: Vec<SummedDay>,
+    },
+    Goals {
+        goals: Vec<GoalProgress>,
+    },
+    Cadences {
+        cadences: Vec<GraphCadence>,
+    },
+    RestDays {
+        days: Vec<String>,
+    },
+    Settings {
+        settings: Vec<Setting>,
+    },
+    Aliases {
+        aliases: Vec<GraphAlias>,
+    },
+    UserData {
+        name: String,
+        sum_graphs: Option<SumGraphsStruct>,
+    },
+    Reminder {
+        missing: Vec<String>,
+    },
+    Profile {
+        name: String,
+        url: String,
+        profile: Profile,
+    },
+    Timer {
+        graph: String,
+        // local "yyyy-mm-dd hh:mm"
+        started: String,
+        elapsed_seconds: i64,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub struct SummedDay {
+    pub date: String,
+    // sum graphs that were sent, unchanged ones are skipped
+    pub summed: Vec<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SentPixel {
+    pub graph: String,
+    pub quantity: String,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PixelNote {
+    pub date: String,
+    pub quantity: f64,
+    pub note: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Setting {
+    pub key: String,
+    pub value: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GraphAlias {
+    pub alias: String,
+    pub graph: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GoalProgress {
+    pub graph: String,
+    pub target: f64,
+    pub period: GoalPeriod,
+    pub progress: f64,
+    pub met: bool,
+    pub today: f64,
+    pub week: f64,
+    pub month: f64,
+}
+
+impl Output {
+    pub fn message(message: impl Into<String>) -> Output {
+        Output::Message { message: message.into() }
+    }
+
+    // some parts of the command failed even though the command itself finished
+    pub fn failed(&self) -> bool {
+        match self {
+            Output::Sums { days } => days.iter().any(|day| day.error.is_some()),
+            Output::Sent { sum_error, .. } => sum_error.is_some(),
+            Output::Batch { pixels, sum_error, .. } => sum_error.is_some() || pixels.iter().any(|pixel| pixel.error.is_some()),
+            _ => false,
+        }
+    }
+}
+
+pub fn render(output: &Output, format: &OutputFormat) -> String {
+    match format {
+        OutputFormat::Json => serde_json::to_string_pretty(output).expect("Outputs are always serializable"),
+        OutputFormat::Plain => render_plain(output),
+        OutputFormat::Table => render_table(output),
+    }
+}
+
+pub fn render_error(err: &Error, format: &OutputFormat) -> String {
+    match format {
+        OutputFormat::Json => serde_json::to_string_pretty(&serde_json::json!({
+            "kind": "error",
+            "error": err.to_string(),
+            "exit_code": err.exit_code(),
+            "hint": err.hint(),
+        })).expect("Errors are always serializable"),
+        OutputFormat::Plain | OutputFormat::Table => match err.hint() {
+            Some(hint) => format!("Error: {err}\nHint: {hint}"),
+            None => format!("Error: {err}"),
+        },
+    }
+}
+
+fn render_plain(output: &Output) -> String {
+    match output {
+        Output::Message { message } => message.clone(),
+        Output::Sent { message, summed, sum_error, .. } => {
+            let mut lines = vec![format!("Success! API responded with: {message}")];
+            summed.iter().for_each(|graph| lines.push(format!("Summed {graph}.")));
+            if let Some(err) = sum_error {
+                lines.push(format!("There was an error while updating sum graphs. {err}"));
+            }
+            lines.join("\n")
+        }
+        Output::Batch { pixels, summed, sum_error, .. } => {
+            let mut lines: Vec<String> = pixels.iter().map(|pixel| match &pixel.error {
+                Some(err) => format!("{}: failed. {err}", pixel.graph),
+                None => format!("{}: sent {}.", pixel.graph, pixel.quantity),
+            }).collect();
+            summed.iter().for_each(|graph| lines.push(format!("Summed {graph}.")));
+            if let Some(err) = sum_error {
+                lines.push(format!("There was an error while updating sum graphs. {err}"));
+            }
+            lines.join("\n")
+        }
+        Output::Pixel { graph, date, quantity, note } => match note {
+            Some(note) => format!("Heatmap name: {graph}\nPixel date: {date}\nCommits amount: {quantity}\nNote: {note}"),
+            None => format!("Heatmap name: {graph}\nPixel date: {date}\nCommits amount: {quantity}"),
+        },
+        Output::Notes { graph, notes } if notes.is_empty() => format!("No notes found for {graph}."),
+        Output::Notes { notes, .. } => notes.iter().map(|note| format!("{} ({}): {}", note.date, note.quantity, note.note)).collect::<Vec<_>>().join("\n"),
+        Output::Graphs { graphs, aliases } => graphs.iter().map(|graph| match aliases_of(aliases, graph).as_str() {
+            "" => format!("Graph Name: {graph}"),
+            names => format!("Graph Name: {graph} ({names})"),
+        }).collect::<Vec<_>>().join("\n"),
+        Output::Streak { graph, streak, goal_period: Some(GoalPeriod::Week), .. } => format!("Your streak for the {graph} is {streak} week(s) long! (weekly goal met every week)"),
+        Output::Streak { graph, streak, goal_period: Some(GoalPeriod::Month), .. } => format!("Your streak for the {graph} is {streak} month(s) long! (monthly goal met every month)"),
+        Output::Streak { graph, streak, cadence, .. } => prepare_cadence_streak_string(*streak, graph, cadence),
+        Output::Sums { days } if days.len() == 1 && days[0].error.is_none() => {
+            let mut lines: Vec<String> = days[0].summed.iter().map(|graph| format!("Summed {graph}.")).collect();
+            lines.push("Success! Your Sum Graph has been updated.".to_string());
+            lines.join("\n")
+        }
+        Output::Sums { days } => days.iter().map(|day| match (&day.error, day.summed.is_empty()) {
+            (Some(err), _) => format!("{}: failed. {err}", day.date),
+            (None, true) => format!("{}: already up to date.", day.date),
+            (None, false) => format!("{}: summed {}.", day.date, day.summed.join(", ")),
+        }).collect::<Vec<_>>().join("\n"),
+        Output::Goals { goals } => goals.iter().map(|goal| {
+            let status = if goal.met { "met" } else { "not met" };
+            format!("Goal for {}: {} per {} ({})\n  Today: {}\n  This week: {}\n  This month: {}", goal.graph, goal.target, goal.period, status, goal.today, goal.week, goal.month)
+        }).collect::<Vec<_>>().join("\n"),
+        Output::Cadences { cadences } if cadences.is_empty() => "All of your graphs are daily habits. (habitcli cadence set -h)".to_string(),
+        Output::Cadences { cadences } => cadences.iter().map(|cadence| format!("{}: {}", cadence.graph, cadence.cadence)).collect::<Vec<_>>().join("\n"),
+        Output::RestDays { days } if days.is_empty() => "You have no rest days.".to_string(),
+        Output::RestDays { days } => days.join("\n"),
+        Output::Settings { settings } => settings.iter().map(|setting| format!("{} = {}", setting.key, setting.value)).collect::<Vec<_>>().join("\n"),
+        Output::Aliases { aliases } if aliases.is_empty() => "You have no aliases. (habitcli alias add -h)".to_string(),
+        Output::Aliases { aliases } => aliases.iter().map(|alias| format!("{} -> {}", alias.alias, alias.graph)).collect::<Vec<_>>().join("\n"),
+        Output::UserData { name, sum_graphs } => match sum_graphs {
+            Some(sum_graphs) => format!("Username: {name}\nSum Graphs Info:\n{sum_graphs}"),
+            None => format!("Username: {name}\nNo sum graphs set up."),
+        },
+        Output::Reminder { missing } if missing.is_empty() => "All graphs are done for today.".to_string(),
+        Output::Reminder { missing } => format!("No pixel for today in: {}", missing.join(", ")),
+        Output::Profile { name, url, profile } => {
+            let mut lines = vec![format!("Username: {name}"), format!("Profile: {url}")];
+            lines.extend(profile_fields(profile).into_iter().map(|(field, value)| format!("{field}: {value}")));
+            lines.join("\n")
+        }
+        Output::Timer { graph, started, elapsed_seconds } => format!("Timing {graph} since {started} ({}).", format_elapsed(*elapsed_seconds)),
+    }
+}
+
+fn render_table(output: &Output) -> String {
+    match output {
+        Output::Message { message } => message.clone(),
+        Output::Sent { graph, date, quantity, message, summed, sum_error } => table(
+            &["GRAPH", "DATE", "QUANTITY", "RESPONSE", "SUMMED"],
+            vec![vec![graph.clone(), date.clone(), quantity.clone(), message.clone(), sum_error.clone().unwrap_or_else(|| summed.join(", "))]],
+        ),
+        Output::Batch { date, pixels, .. } => table(
+            &["GRAPH", "DATE", "QUANTITY", "RESULT"],
+            pixels.iter().map(|pixel| vec![pixel.graph.clone(), date.clone(), pixel.quantity.clone(), pixel.error.clone().unwrap_or_else(|| "sent".to_string())]).collect(),
+        ),
+        Output::Pixel { graph, date, quantity, note } => table(&["GRAPH", "DATE", "QUANTITY", "NOTE"], vec![vec![graph.clone(), date.clone(), quantity.to_string(), note.clone().unwrap_or_default()]]),
+        Output::Notes { notes, .. } => table(&["DATE", "QUANTITY", "NOTE"], notes.iter().map(|note| vec![note.date.clone(), note.quantity.to_string(), note.note.clone()]).collect()),
+        Output::Graphs { graphs, aliases } => table(&["GRAPH", "ALIASES"], graphs.iter().map(|graph| vec![graph.clone(), aliases_of(aliases, graph)]).collect()),
+        Output::Streak { graph, streak, cadence, goal_period } => {
+            let unit = match goal_period {
+                Some(GoalPeriod::Week) => "weekly goal".to_string(),
+                Some(GoalPeriod::Month) => "monthly goal".to_string(),
+                _ => cadence.to_string(),
+            };
+            table(&["GRAPH", "STREAK", "CADENCE"], vec![vec![graph.clone(), streak.to_string(), unit]])
+        }
+        Output::Sums { days } => table(&["DATE", "SUMMED", "ERROR"], days.iter().map(|day| vec![day.date.clone(), day.summed.join(", "), day.error.clone().unwrap_or_default()]).collect()),
+        Output::Goals { goals } => table(
+            &["GRAPH", "TARGET", "PER", "MET", "TODAY", "WEEK", "MONTH"],
+            goals.iter().map(|goal| vec![goal.graph.clone(), goal.target.to_string(), goal.period.to_string(), goal.met.to_string(), goal.today.to_string(), goal.week.to_string(), goal.month.to_string()]).collect(),
+        ),
+        Output::Cadences { cadences } => table(&["GRAPH", "CADENCE"], cadences.iter().map(|cadence| vec![cadence.graph.clone(), cadence.cadence.to_string()]).collect()),
+        Output::RestDays { days } => table(&["REST DAY"], days.iter().map(|day| vec![day.clone()]).collect()),
+        Output::Settings { settings } => table(&["SETTING", "VALUE"], settings.iter().map(|setting| vec![setting.key.clone(), setting.value.clone()]).collect()),
+        Output::Aliases { aliases } => table(&["ALIAS", "GRAPH"], aliases.iter().map(|alias| vec![alias.alias.clone(), alias.graph.clone()]).collect()),
+        Output::UserData { name, sum_graphs } => table(
+            &["USERNAME", "SUM GRAPH", "GRAPHS"],
+            match sum_graphs {
+                Some(sum_graphs) => sum_graphs.sum_graphs.iter().map(|graph| vec![name.clone(), graph.sum_graph_name.clone(), graph.graphs_to_sum.join(", ")]).collect(),
+                None => vec![vec![name.clone(), String::new(), String::new()]],
+            },
+        ),
+        Output::Reminder { missing } => table(&["MISSING TODAY"], missing.iter().map(|graph| vec![graph.clone()]).collect()),
+        Output::Profile { name, url, profile } => table(
+            &["FIELD", "VALUE"],
+            [("Username", name.clone()), ("Profile", url.clone())].into_iter().chain(profile_fields(profile))
+                .map(|(field, value)| vec![field.to_string(), value]).collect(),
+        ),
+        Output::Timer { graph, started, elapsed_seconds } => table(&["GRAPH", "STARTED", "ELAPSED"], vec![vec![graph.clone(), started.clone(), format_elapsed(*elapsed_seconds)]]),
+    }
+}
+
+// fields of a profile that are set, with readable names
+fn profile_fields(profile: &Profile) -> Vec<(&'static str, String)> {
+    [
+        ("Display name", profile.display_name.clone()),
+        ("Title", profile.title.clone()),
+        ("Timezone", profile.timezone.clone()),
+        ("About", profile.about_url.clone()),
+        ("Gravatar email", profile.gravatar_icon_email.clone()),
+        ("Contributes to", profile.contribute_urls.as_ref().map(|urls| urls.join(", "))),
+        ("Pinned graph", profile.pinned_graph_id.clone()),
+    ].into_iter().filter_map(|(field, value)| value.filter(|value| !value.is_empty()).map(|value| (field, value))).collect()
+}
+
+pub fn format_elapsed(seconds: i64) -> String {
+    format!("{}h {:02}m {:02}s", seconds / 3600, seconds % 3600 / 60, seconds % 60)
+}
+
+fn aliases_of(aliases: &[GraphAlias], graph: &str) -> String {
+    aliases.iter().filter(|alias| alias.graph == graph).map(|alias| alias.alias.as_str()).collect::<Vec<_>>().join(", ")
+}
+
+fn table(headers: &[&str], rows: Vec<Vec<String>>) -> String {
+    // left aligned columns as wide as their widest cell
+    let mut widths: Vec<usize> = headers.iter().map(|header| header.len()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let format_row = |cells: Vec<String>| {
+        cells.iter().zip(&widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    let mut lines = vec![format_row(headers.iter().map(|header| header.to_string()).collect())];
+    lines.extend(rows.into_iter().map(format_row));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn table_alignment() {
+        let rendered = table(&["GRAPH", "QUANTITY"], vec![vec!["reading".to_string(), "30".to_string()], vec!["gym".to_string(), "1".to_string()]]);
+        assert_eq!(rendered, "GRAPH    QUANTITY\nreading  30\ngym      1");
+    }
+
+    #[test]
+    fn json_output() {
+        let output = Output::Pixel { graph: "rd1".to_string(), date: "20240301".to_string(), quantity: 30.0, note: None };
+        let json: serde_json::Value = serde_json::from_str(&render(&output, &OutputFormat::Json)).unwrap();
+        assert_eq!(json, serde_json::json!({"kind": "pixel", "graph": "rd1", "date": "20240301", "quantity": 30.0, "note": null}));
+
+        let streak = Output::Streak { graph: "gym".to_string(), streak: 2, cadence: Cadence::Week { times: 3 }, goal_period: None };
+        let json: serde_json::Value = serde_json::from_str(&render(&streak, &OutputFormat::Json)).unwrap();
+        assert_eq!(json["cadence"], serde_json::json!({"every": "week", "times": 3}));
+        assert!(json.get("goal_period").is_none());
+
+        let streak = Output::Streak { graph: "rd1".to_string(), streak: 3, cadence: Cadence::Daily, goal_period: Some(GoalPeriod::Week) };
+        assert!(render(&streak, &OutputFormat::Plain).contains("3 week(s)"));
+    }
+
+    #[test]
+    fn timer_output() {
+        let timer = Output::Timer { graph: "rd1".to_string(), started: "2024-03-01 20:15".to_string(), elapsed_seconds: 3725 };
+        assert_eq!(render(&timer, &OutputFormat::Plain), "Timing rd1 since 2024-03-01 20:15 (1h 02m 05s).");
+    }
+}
@@ -1,234 +1,534 @@
-use std::sync::Arc;
+use std::{collections::{HashMap, HashSet}, sync::Arc, time::Duration};
 
 /*
  Api to communicate with Pixe.la web api
  */
-use chrono::{self, NaiveDate};
-use reqwest::Client;
+use chrono::{self, Datelike, NaiveDate};
+use reqwest::{Client, Method, RequestBuilder};
+use serde::{Deserialize, Serialize};
 use serde_json::{self, Value};
 use tokio::sync::Mutex;
 use crate::error::{self};
+use crate::user_data::{Cadence, GoalPeriod};
 
 
 type Result<T> = error::Result<T>;
 
+pub const PIXELA_URL: &str = "https://pixe.la/v1";
+
+#[derive(Clone)]
 pub struct Session {
     client: Client,
+    base_url: String,
+    // applied to every request, without it a request can hang forever
+    timeout: Option<Duration>,
+    // extra attempts of a request that was rejected on purpose or failed on the way
+    retries: u32,
+    retry_delay: Duration,
+}
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 impl Session {
     pub fn new() -> Session {
         let client = Client::new();
-        Session {client}
+        Session {client, base_url: PIXELA_URL.to_string(), timeout: None, retries: 0, retry_delay: Duration::ZERO}
+    }
+    pub fn with_base_url(mut self, base_url: &str) -> Session {
+        self.base_url = base_url.trim_end_matches('/').to_string();
+        self
+    }
+    pub fn with_timeout(mut self, timeout: Duration) -> Session {
+        self.timeout = Some(timeout);
+        self
+    }
+    pub fn with_retries(mut self, retries: u32, delay: Duration) -> Session {
+        self.retries = retries;
+        self.retry_delay = delay;
+        self
+    }
+
+    pub fn users_url(&self) -> String {
+        format!("{}/users", self.base_url)
+    }
+    pub fn graphs_url(&self, username: &str) -> String {
+        format!("{}/users/{username}/graphs", self.base_url)
+    }
+    pub fn graph_url(&self, username: &str, graph: &str) -> String {
+        format!("{}/users/{username}/graphs/{graph}", self.base_url)
     }
 
-    pub(crate) async fn get_pixel_info(
+    fn request(&self, method: Method, url: &str) -> RequestBuilder {
+        let request = self.client.request(method, url);
+        match self.timeout {
+            Some(timeout) => request.timeout(timeout),
+            None => request,
+        }
+    }
+    async fn send(&self, mut request: RequestBuilder) -> Result<Value> {
+        let mut retries = self.retries;
+        // a write that timed out may have been applied already, sending it again could apply it twice (like PUT .../add)
+        let is_get = request.try_clone().and_then(|request| request.build().ok()).is_some_and(|request| request.method() == Method::GET);
+        loop {
+            let retry = if retries > 0 { request.try_clone() } else { None };
+            // transport and decoding failures end up as ReqwestError instead of panicking
+            let response: Result<Value> = match request.send().await {
+                Ok(response) => response.json().await.map_err(error::Error::from),
+                Err(err) => Err(err.into()),
+            };
+            let Some(retry) = retry else { return response };
+            match &response {
+                // non-supporters get a share of requests rejected with "Please retry this request"
+                Ok(response) if response.get("isRejected") == Some(&Value::Bool(true)) => (),
+                Err(error::Error::ReqwestError(err)) if err.is_connect() || (is_get && err.is_timeout()) => (),
+                _ => return response,
+            }
+            tokio::time::sleep(self.retry_delay).await;
+            retries -= 1;
+            request = retry;
+        }
+    }
+
+    pub async fn get_pixel_info(
         &self,
         url: &str,
         name: &str,
         date: Option<&str>,
         token: &str,
     ) -> Result<CallResult> {
-        let client = &self.client;
         let date: &str = match date {
             Some(date) => date,
             None => &chrono::Local::now().format("%Y%m%d").to_string(),
         };
         let url = format!("{url}/{date}");
 
-        let response = client.get(url).header("X-USER-TOKEN", token).send();
-        let response: serde_json::Value = response.await.unwrap().json().await.map_err(|err| error::Error::ReqwestError(err))?;
+        let response = self.send(self.request(Method::GET, &url).header("X-USER-TOKEN", token)).await?;
+        reject_unauthorized(&response)?;
 
-        let quantity: u32 = match response.get("quantity") {
-            Some(quantity) => quantity.as_str().unwrap().parse().unwrap(),
-            None => 0,
-        };
         Ok(CallResult::Heatmap(Heatmap::new(
             name.to_string(),
             date.to_string(),
-            quantity,
+            parse_quantity(&response)?,
         )))
     }
-    pub(crate) async fn send_pixel(
+    pub async fn send_pixel(
         &self,
         url: &str,
         quantity: &str,
         date: Option<&str>,
+        optional_data: Option<&str>,
         token: &str,
     ) -> Result<CallResult> {
-        let client = &self.client;
         let date: &str = match date {
             Some(date) => date,
             None => &chrono::Local::now().format("%Y%m%d").to_string(),
         };
-        let response = client
-            .post(url)
+        let mut body = serde_json::json!({
+            "date": date,
+            "quantity": quantity
+        });
+        if let Some(optional_data) = optional_data {
+            body["optionalData"] = Value::String(optional_data.to_string());
+        }
+        let request = self.request(Method::POST, url)
             .header("X-USER-TOKEN", token)
-            .json(&serde_json::json!({
-                "date": date,
-                "quantity": quantity
-            }))
-            .send();
-
-        let response: serde_json::Value = response.await.unwrap().json().await.map_err(|err| error::Error::ReqwestError(err))?;
+            .json(&body);
+        let response = self.send(request).await?;
 
         Ok(CallResult::ApiResponse(Message::new(response)))
     }
-    pub(crate) async fn get_graph_list(
+    pub async fn get_graph_list(
         &self,
         token: &str,
         url: &str
     ) -> Result<CallResult> {
-        let client = &self.client;
-        let response = client.get(url).header("X-USER-TOKEN", token).send();
-        let response: serde_json::Value = response.await.unwrap().json().await.map_err(|err| error::Error::ReqwestError(err))?;
-        let graphs = if let Some(graphs) = response.get("graphs") {graphs.to_owned()} else { return Err(error::Error::MissingEntryInDatabase("No graphs to display".to_string()))}; // dfq error handling
-        let graphs: Vec<String> = graphs.as_array().unwrap()
-            .iter()
-            .map(|obj| obj.get("id").unwrap().to_string().replace("/", "").replace('"', "").to_string())
+        let graphs: Vec<String> = self.get_graph_definitions(token, url).await?
+            .into_iter()
+            .map(|graph| graph.id.replace('/', ""))
             .collect();
         Ok(CallResult::List(graphs))
-
-       
+    }
+    pub async fn get_graph_definitions(
+        &self,
+        token: &str,
+        url: &str
+    ) -> Result<Vec<GraphDefinition>> {
+        let response = self.send(self.request(Method::GET, url).header("X-USER-TOKEN", token)).await?;
+        let graphs = if let Some(graphs) = response.get("graphs") {graphs.to_owned()} else { return Err(rejection(&response)) };
+        Ok(serde_json::from_value(graphs)?)
     }
     pub async fn create_user(&self, user_specified_token: &str, username: &str, not_minor:bool, tos:bool) -> Result<()> {
-        let client = &self.client;
-            let url = format!("https://pixe.la/v1/users/");
-            if !tos || !not_minor {
-                return Err(error::Error::PixelaError(String::from("You didn't agree to TOS or you're a minor.")));
-            }
-        let response = client
-            .post(url)
+        if !tos {
+            return Err(error::Error::InvalidInput(String::from("Pixela accounts can only be created after agreeing to its terms of service.")));
+        }
+        if !not_minor {
+            return Err(error::Error::InvalidInput(String::from("Minors need their guardian's consent to create a Pixela account.")));
+        }
+        let request = self.request(Method::POST, &self.users_url())
             .json(&serde_json::json!({
                 "token": user_specified_token,
                 "username": username,
                 "agreeTermsOfService": "yes",
                 "notMinor": "yes"
-            }))
-            .send();
-        let response: serde_json::Value = response.await.unwrap().json().await.map_err(|err| error::Error::ReqwestError(err))?;
-        match response.get("isSuccess") {
-            None => return Err(error::Error::PixelaError(response.get("message").unwrap().to_string())),
-            Some(message) => {if message == false {return Err(error::Error::PixelaError(response.get("message").unwrap().to_string()))}}
+            }));
+        let response = self.send(request).await?;
+        if response.get("isSuccess") != Some(&Value::Bool(true)) {
+            return Err(rejection(&response));
         }
-        return Ok(())
+        Ok(())
     }
-    pub async fn create_graph(&self, username: &str, token: &str, id: &str, name: &str, number_type: &str, unit: &str, color: &str) -> Result<()> {
-        validate_args(color, number_type)?;
-        let response = self.client
-            .post(format!("https://pixe.la/v1/users/{}/graphs", username ))
+    pub async fn update_token(&self, username: &str, token: &str, new_token: &str) -> Result<()> {
+        let request = self.request(Method::PUT, &format!("{}/{username}", self.users_url()))
+            .header("X-USER-TOKEN", token)
+            .json(&serde_json::json!({ "newToken": new_token }));
+        let response = self.send(request).await?;
+        if response.get("isSuccess") != Some(&Value::Bool(true)) {
+            return Err(rejection(&response));
+        }
+        Ok(())
+    }
+    pub async fn delete_user(&self, username: &str, token: &str) -> Result<()> {
+        let request = self.request(Method::DELETE, &format!("{}/{username}", self.users_url()))
+            .header("X-USER-TOKEN", token);
+        let response = self.send(request).await?;
+        if response.get("isSuccess") != Some(&Value::Bool(true)) {
+            return Err(rejection(&response));
+        }
+        Ok(())
+    }
+    pub async fn get_profile(&self, username: &str, token: &str) -> Result<Profile> {
+        let request = self.request(Method::GET, &format!("{}/{username}", self.users_url()))
+            .header("X-USER-TOKEN", token);
+        let response = self.send(request).await?;
+        if response.get("isSuccess") == Some(&Value::Bool(false)) {
+            return Err(rejection(&response));
+        }
+        Ok(serde_json::from_value(response)?)
+    }
+    // only the fields that are set are sent
+    pub async fn update_profile(&self, username: &str, token: &str, profile: &Profile) -> Result<()> {
+        let request = self.request(Method::PUT, &self.profile_url(username))
+            .header("X-USER-TOKEN", token)
+            .json(profile);
+        let response = self.send(request).await?;
+        if response.get("isSuccess") != Some(&Value::Bool(true)) {
+            return Err(rejection(&response));
+        }
+        Ok(())
+    }
+    // the public profile page answers without a token, so it tells a missing user from a wrong token
+    pub async fn user_exists(&self, username: &str) -> Result<bool> {
+        let response = self.request(Method::GET, &self.profile_url(username)).send().await?;
+        Ok(response.status() != reqwest::StatusCode::NOT_FOUND)
+    }
+    pub fn profile_url(&self, username: &str) -> String {
+        format!("{}/@{username}", self.base_url.trim_end_matches("/v1"))
+    }
+    pub async fn create_graph(&self, username: &str, token: &str, graph: &GraphDefinition) -> Result<()> {
+        validate_args(&graph.color, &graph.number_type)?;
+        let request = self.request(Method::POST, &self.graphs_url(username))
             .header("X-USER-TOKEN", token)
             .json(&serde_json::json!(
                     {
-                        "id": id,
-                        "name": name,
-                        "type": number_type,
-                        "unit": unit,
-                        "color": color,
+                        "id": graph.id,
+                        "name": graph.name,
+                        "type": graph.number_type,
+                        "unit": graph.unit,
+                        "color": graph.color,
                     }
-                ))
-            .send();
-        let response: serde_json::Value = response.await.unwrap().json().await.map_err(|err| error::Error::PixelaError(err.to_string()))?;
-        match response.get("isSuccess") {
-            None => return Err(error::Error::PixelaError(response.get("message").unwrap().to_string())),
-            Some(message) => {if message == false {return Err(error::Error::PixelaError(response.get("message").unwrap().to_string()))}}
+                ));
+        let response = self.send(request).await?;
+        if response.get("isSuccess") != Some(&Value::Bool(true)) {
+            return Err(rejection(&response));
         }
-        return Ok(());
+        Ok(())
     }
     pub async fn get_streak(&self, username: &str, token: &str, graph_name: &str) -> Result<u32>{
-        let client = &self.client;
-        let url = format!("https://pixe.la/v1/users/{}/graphs/{}/pixels", username, graph_name);
+        let pixels = self.get_pixels(username, token, graph_name).await?;
+        Ok(count_streak(&pixels, chrono::Local::now().date_naive(), &HashSet::new(), |pixel| pixel.quantity != 0.0))
+    }
+    // one pixel with its note, the pixel list only reaches back a year
+    pub async fn get_pixel(&self, username: &str, token: &str, graph_name: &str, date: NaiveDate) -> Result<Option<Pixel>> {
+        let url = format!("{}/{}", self.graph_url(username, graph_name), date.format("%Y%m%d"));
+        let response = self.send(self.request(Method::GET, &url).header("X-USER-TOKEN", token)).await?;
+        reject_unauthorized(&response)?;
+        match response.get("quantity") {
+            Some(Value::String(quantity)) => Ok(Some(Pixel {
+                date,
+                quantity: quantity.parse().map_err(|_| error::Error::PixelaError(response.to_string()))?,
+                optional_data: response.get("optionalData").and_then(Value::as_str).map(str::to_string),
+            })),
+            Some(_) => Err(error::Error::PixelaError(response.to_string())),
+            // pixels that don't exist yet have no quantity
+            None => Ok(None),
+        }
+    }
+    pub async fn get_pixels(&self, username: &str, token: &str, graph_name: &str) -> Result<Vec<Pixel>>{
+        let url = format!("{}/pixels", self.graph_url(username, graph_name));
 
-        let response = client.get(url).header("X-USER-TOKEN", token).query(&serde_json::json!(
+        let request = self.request(Method::GET, &url).header("X-USER-TOKEN", token).query(&serde_json::json!(
+                    {
+                        "withBody": "true",
+                    }
+                ));
+        self.send_pixels(request).await
+    }
+    // without from and to pixela only lists the last year, a longer range has to be asked for a year at a time
+    pub async fn get_pixels_between(&self, username: &str, token: &str, graph_name: &str, from: NaiveDate, to: NaiveDate) -> Result<Vec<Pixel>> {
+        let url = format!("{}/pixels", self.graph_url(username, graph_name));
+        let request = self.request(Method::GET, &url).header("X-USER-TOKEN", token).query(&serde_json::json!(
                     {
                         "withBody": "true",
+                        "from": from.format("%Y%m%d").to_string(),
+                        "to": to.format("%Y%m%d").to_string(),
                     }
-                )).send();
-        let response: serde_json::Value = response.await.unwrap().json().await.map_err(|err| error::Error::ReqwestError(err))?;
-        match response.get("pixels") {
-            None => return Err(error::Error::PixelaError(response.get("message").unwrap().to_string())),
-            Some(message) => {if message == false {return Err(error::Error::PixelaError(response.get("message").unwrap().to_string()))}}
+                ));
+        self.send_pixels(request).await
+    }
+    async fn send_pixels(&self, request: RequestBuilder) -> Result<Vec<Pixel>> {
+        let response = self.send(request).await?;
+        if response.get("pixels").is_none() {
+            return Err(rejection(&response));
         }
-        Ok(calculate_streak(response))
+        parse_pixels(&response)
     }
-    pub async fn async_get_graph_val( 
+    pub async fn async_get_graph_val(
+        &self,
         url: &str,
         date: &str,
         token: &str,
         incr_pointer: Arc<Mutex<u32>>,
     ) -> Result<()> {
-        let client = Client::new();
         let url = format!("{url}/{date}");
 
-        let response = client.get(url).header("X-USER-TOKEN", token).send().await;
-        let response: serde_json::Value = response.unwrap().json().await.map_err(|err| error::Error::ReqwestError(err))?;
+        let response = self.send(self.request(Method::GET, &url).header("X-USER-TOKEN", token)).await?;
+        reject_unauthorized(&response)?;
 
-        let quantity: u32 = match response.get("quantity") {
-            Some(quantity) => quantity.as_str().unwrap().parse::<u32>().map_err(|_| error::Error::PixelaError(response.to_string()))?,
-            None => 0,
-        };
-        *incr_pointer.lock().await += quantity;
+        *incr_pointer.lock().await += parse_quantity(&response)?;
         Ok(())
     }
-    pub async fn remove_graph(&self, username: &str, token: &str, graph_name: &str) -> Result<()> {
-        let response = self.client
-            .delete(format!("https://pixe.la/v1/users/{username}/graphs/{graph_name}"))
+    pub async fn delete_pixel(&self, url: &str, date: &str, token: &str) -> Result<()> {
+        let request = self.request(Method::DELETE, &format!("{url}/{date}"))
+            .header("X-USER-TOKEN", token);
+        let response = self.send(request).await?;
+        // a pixel that was never sent is already cleared
+        let missing = response.get("message").and_then(Value::as_str).is_some_and(|message| message.contains("pixel not found"));
+        if response.get("isSuccess") != Some(&Value::Bool(true)) && !missing {
+            return Err(rejection(&response));
+        }
+        Ok(())
+    }
+    // adds to today's pixel on pixela's side, today is in the graph's timezone
+    pub async fn add_quantity(&self, url: &str, quantity: &str, token: &str) -> Result<()> {
+        let request = self.request(Method::PUT, &format!("{url}/add"))
             .header("X-USER-TOKEN", token)
-            .send();
-        let response: serde_json::Value = response.await.unwrap().json().await.map_err(|err| error::Error::PixelaError(err.to_string()))?;
-        match response.get("isSuccess") {
-            None => return Err(error::Error::PixelaError(response.get("message").unwrap().to_string())),
-            Some(message) => {if message == false {return Err(error::Error::PixelaError(response.get("message").unwrap().to_string()))}}
+            .json(&serde_json::json!({ "quantity": quantity }));
+        let response = self.send(request).await?;
+        if response.get("isSuccess") != Some(&Value::Bool(true)) {
+            return Err(rejection(&response));
         }
-        return Ok(());
+        Ok(())
+    }
+    pub async fn remove_graph(&self, username: &str, token: &str, graph_name: &str) -> Result<()> {
+        let request = self.request(Method::DELETE, &self.graph_url(username, graph_name))
+            .header("X-USER-TOKEN", token);
+        let response = self.send(request).await?;
+        if response.get("isSuccess") != Some(&Value::Bool(true)) {
+            return Err(rejection(&response));
+        }
+        Ok(())
     }
 
 }
 
+fn parse_quantity(response: &Value) -> Result<u32> {
+    // pixels that don't exist yet have no quantity
+    match response.get("quantity") {
+        Some(Value::String(quantity)) => quantity.parse::<u32>().map_err(|_| error::Error::PixelaError(response.to_string())),
+        Some(_) => Err(error::Error::PixelaError(response.to_string())),
+        None => Ok(0),
+    }
+}
+
+fn rejection(response: &Value) -> error::Error {
+    // pixela doesn't have a separate error code for bad credentials, only the message tells
+    let message = match response.get("message") {
+        Some(Value::String(message)) => message.clone(),
+        _ => response.to_string(),
+    };
+    if message.contains("token is wrong") || (message.contains("does not exist") && message.contains("User")) {
+        error::Error::AuthError(message)
+    } else {
+        error::Error::PixelaError(message)
+    }
+}
+
+fn reject_unauthorized(response: &Value) -> Result<()> {
+    // a missing pixel is a rejection too, but it only means that there is no pixel yet
+    if response.get("isSuccess") == Some(&Value::Bool(false)) {
+        if let error::Error::AuthError(message) = rejection(response) {
+            return Err(error::Error::AuthError(message));
+        }
+    }
+    Ok(())
+}
+
 // Functions not tied to the Pixela web api below
 pub fn calculate_streak(pixels: Value) -> u32 {
-    // calculates streak and prints string visualizing the streak
-    let pixels_array = pixels.get("pixels").unwrap().as_array().unwrap();
-    if pixels_array.len() == 0 { return 0 }
-    let mut streak = 0;
+    // calculates a streak of consecutive days with any commits
+    match parse_pixels(&pixels) {
+        Ok(pixels) => count_streak(&pixels, chrono::Local::now().date_naive(), &HashSet::new(), |pixel| pixel.quantity != 0.0),
+        Err(_) => 0,
+    }
+}
 
-    let mut streak_going = false;
-    let mut streak_valid = false;
+pub fn parse_pixels(response: &Value) -> Result<Vec<Pixel>> {
+    // turns pixela's "pixels" response (requested with body) into typed pixels
+    let malformed = || error::Error::PixelaError(format!("Unexpected pixel list: {response}"));
+    let pixels_array = match response.get("pixels") {
+        Some(Value::Array(pixels)) => pixels,
+        Some(Value::Null) | None => return Ok(Vec::new()),
+        Some(_) => return Err(malformed()),
+    };
+    pixels_array.iter().map(|pixel| {
+        let date = pixel.get("date").and_then(|date| date.as_str()).ok_or_else(malformed)?;
+        let quantity = pixel.get("quantity").and_then(|quantity| quantity.as_str()).ok_or_else(malformed)?;
+        Ok(Pixel {
+            date: NaiveDate::parse_from_str(date, "%Y%m%d").map_err(|_| malformed())?,
+            quantity: quantity.parse().map_err(|_| malformed())?,
+            optional_data: pixel.get("optionalData").and_then(Value::as_str).map(str::to_string),
+        })
+    }).collect()
+}
 
-    // loop to compare dates
-    for i in 1..pixels_array.len() {
-        let date1 = NaiveDate::parse_from_str(pixels_array[i-1].get("date").unwrap().as_str().unwrap(), "%Y%m%d").unwrap();
-        let date2 = NaiveDate::parse_from_str(pixels_array[i].get("date").unwrap().as_str().unwrap(), "%Y%m%d").unwrap();
-        let quantity1 = pixels_array[i-1].get("quantity").unwrap().as_str().unwrap();
-        let quantity2 = pixels_array[i].get("quantity").unwrap().as_str().unwrap();
-        let difference = (date2-date1).num_days();
+pub fn count_streak(pixels: &[Pixel], today: NaiveDate, rest_days: &HashSet<NaiveDate>, counts: impl Fn(&Pixel) -> bool) -> u32 {
+    // counts consecutive counted days back from today, today itself may still be missing
+    let counted: HashSet<NaiveDate> = pixels.iter().filter(|pixel| counts(pixel)).map(|pixel| pixel.date).collect();
+    count_days(&counted, rest_days, today, true, |day| day - chrono::Days::new(1))
+}
 
-        if difference > 1 || quantity1 == "0" || quantity2 == "0" {
-            streak_going = false;
-            streak = 0;
+fn count_days(counted: &HashSet<NaiveDate>, rest_days: &HashSet<NaiveDate>, mut day: NaiveDate, in_progress: bool, previous: impl Fn(NaiveDate) -> NaiveDate) -> u32 {
+    // rest days neither count nor break the streak
+    if in_progress && !counted.contains(&day) {
+        day = previous(day);
+    }
+    let mut streak = 0;
+    loop {
+        if counted.contains(&day) {
+            streak += 1;
+        } else if !rest_days.contains(&day) {
+            return streak;
         }
-        else if difference == 1 && streak_going {
-            streak += 1
+        day = previous(day);
+    }
+}
+
+pub fn cadence_streak(pixels: &[Pixel], today: NaiveDate, cadence: &Cadence, rest_days: &HashSet<NaiveDate>, counts: impl Fn(&Pixel) -> bool) -> u32 {
+    // counts a streak in units of the cadence, the current unit may still be in progress
+    let counted: HashSet<NaiveDate> = pixels.iter().filter(|pixel| counts(pixel)).map(|pixel| pixel.date).collect();
+    match cadence {
+        Cadence::Daily => count_streak(pixels, today, rest_days, counts),
+        Cadence::Weekdays => {
+            let is_weekend = |day: NaiveDate| day.weekday().number_from_monday() > 5;
+            let previous_weekday = |mut day: NaiveDate| {
+                day = day - chrono::Days::new(1);
+                while is_weekend(day) {
+                    day = day - chrono::Days::new(1);
+                }
+                day
+            };
+            if is_weekend(today) {
+                count_days(&counted, rest_days, previous_weekday(today), false, previous_weekday)
+            } else {
+                count_days(&counted, rest_days, today, true, previous_weekday)
+            }
         }
-        if !streak_going && quantity2 != "0" {
+        Cadence::Week { times } => count_periods(&counted, rest_days, week_start(today), week_start, previous_week, *times),
+        Cadence::Month { times } => count_periods(&counted, rest_days, month_start(today), month_start, previous_month, *times),
+    }
+}
+
+// weeks and months are identified by their first day
+fn week_start(day: NaiveDate) -> NaiveDate {
+    day - chrono::Days::new(day.weekday().num_days_from_monday() as u64)
+}
+fn previous_week(start: NaiveDate) -> NaiveDate {
+    start - chrono::Days::new(7)
+}
+fn month_start(day: NaiveDate) -> NaiveDate {
+    day.with_day(1).expect("Every month has a first day")
+}
+fn previous_month(start: NaiveDate) -> NaiveDate {
+    month_start(start - chrono::Days::new(1))
+}
+
+fn count_periods(counted: &HashSet<NaiveDate>, rest_days: &HashSet<NaiveDate>, current: NaiveDate, start_of: impl Fn(NaiveDate) -> NaiveDate, previous: impl Fn(NaiveDate) -> NaiveDate, times: u32) -> u32 {
+    // a period counts once it has enough counted days and every rest day in a period lowers the amount of days it needs
+    let mut per_period: HashMap<NaiveDate, u32> = HashMap::new();
+    counted.iter().for_each(|day| *per_period.entry(start_of(*day)).or_default() += 1);
+    let mut rest_per_period: HashMap<NaiveDate, u32> = HashMap::new();
+    rest_days.iter().for_each(|day| *rest_per_period.entry(start_of(*day)).or_default() += 1);
+    let days = |start: &NaiveDate| per_period.get(start).copied().unwrap_or(0);
+    let needed = |start: &NaiveDate| times.saturating_sub(rest_per_period.get(start).copied().unwrap_or(0));
+    // nothing before the earliest counted or rest day can count or bridge
+    let Some(earliest) = counted.iter().chain(rest_days).min().map(|day| start_of(*day)) else { return 0 };
+    walk_periods(current, previous, earliest,
+        |start| days(start) > 0 && days(start) >= needed(start),
+        |start| days(start) == 0 && needed(start) == 0)
+}
+
+fn walk_periods(current: NaiveDate, previous: impl Fn(NaiveDate) -> NaiveDate, earliest: NaiveDate, met: impl Fn(&NaiveDate) -> bool, bridged: impl Fn(&NaiveDate) -> bool) -> u32 {
+    // the current period may still be in progress, so it doesn't break the streak
+    let mut period = if met(&current) { current } else { previous(current) };
+    let mut streak = 0;
+    while period >= earliest {
+        if met(&period) {
             streak += 1;
-            streak_going = true;
+        } else if !bridged(&period) {
+            return streak;
         }
-        
+        period = previous(period);
     }
+    streak
+}
 
-    // Is the streak valid - if there wasn't any commits yesterday it is not
-    let today: NaiveDate = (chrono::Local::now()).date_naive();
-    let yesterday: NaiveDate = today-chrono::Days::new(1);
-    let last_date = NaiveDate::parse_from_str(pixels_array.last().unwrap().get("date").unwrap().as_str().unwrap(), "%Y%m%d").unwrap();
-    if last_date == today || last_date == yesterday {
-        streak_valid = true;
+pub fn goal_streak(pixels: &[Pixel], today: NaiveDate, period: &GoalPeriod, target: f64, rest_days: &HashSet<NaiveDate>) -> u32 {
+    // consecutive weeks or months whose total reached the goal, rest days lower the goal by their share of the period
+    if *period == GoalPeriod::Day {
+        return count_streak(pixels, today, rest_days, |pixel| pixel.quantity >= target);
     }
-
-    if !streak_valid {
-        streak = 0;
+    let weekly = *period == GoalPeriod::Week;
+    let start_of: fn(NaiveDate) -> NaiveDate = if weekly { week_start } else { month_start };
+    let previous: fn(NaiveDate) -> NaiveDate = if weekly { previous_week } else { previous_month };
+    let mut totals: HashMap<NaiveDate, f64> = HashMap::new();
+    pixels.iter().for_each(|pixel| *totals.entry(start_of(pixel.date)).or_default() += pixel.quantity);
+    let mut rest_per_period: HashMap<NaiveDate, u32> = HashMap::new();
+    rest_days.iter().for_each(|day| *rest_per_period.entry(start_of(*day)).or_default() += 1);
+    let total = |start: &NaiveDate| totals.get(start).copied().unwrap_or(0.0);
+    let needed = |start: &NaiveDate| {
+        let length = if weekly { 7 } else { (*start + chrono::Months::new(1) - *start).num_days() };
+        let rest = rest_per_period.get(start).copied().unwrap_or(0) as i64;
+        target * (length - rest).max(0) as f64 / length as f64
     };
+    let worked = pixels.iter().filter(|pixel| pixel.quantity != 0.0).map(|pixel| pixel.date);
+    let Some(earliest) = worked.chain(rest_days.iter().copied()).min().map(start_of) else { return 0 };
+    walk_periods(start_of(today), previous, earliest,
+        |start| total(start) > 0.0 && total(start) >= needed(start),
+        |start| total(start) == 0.0 && needed(start) == 0.0)
+}
 
-    return streak;
+pub fn period_total(pixels: &[Pixel], today: NaiveDate, period: &GoalPeriod) -> f64 {
+    // sum of quantities in the day, ISO week or month that contains today
+    pixels.iter()
+        .filter(|pixel| match period {
+            GoalPeriod::Day => pixel.date == today,
+            GoalPeriod::Week => pixel.date.iso_week() == today.iso_week(),
+            GoalPeriod::Month => pixel.date.year() == today.year() && pixel.date.month() == today.month(),
+        })
+        .map(|pixel| pixel.quantity)
+        .sum()
 }
 
 pub fn prepare_streak_string(days: u32, graph_name: &str) -> String{
@@ -239,21 +539,49 @@ pub fn prepare_streak_string(days: u32, graph_name: &str) -> String{
     
 }
 
+pub fn prepare_cadence_streak_string(streak: u32, graph_name: &str, cadence: &Cadence) -> String {
+    match cadence {
+        Cadence::Daily => prepare_streak_string(streak, graph_name),
+        Cadence::Weekdays => format!("Your streak for the {graph_name} is {streak} weekday(s) long!"),
+        Cadence::Week { times } => format!("Your streak for the {graph_name} is {streak} week(s) long! (at least {times} day(s) every week)"),
+        Cadence::Month { times } => format!("Your streak for the {graph_name} is {streak} month(s) long! (at least {times} day(s) every month)"),
+    }
+}
+
 pub fn validate_args(color: &str, _type: &str) -> Result<()> {
     let valid_colors: [&str; 6] = ["shibafu", "momiji", "sora", "ichou", "ajisai", "kuro"];
     let valid_types: [&str;2] = ["int", "float"];
     if valid_colors.contains(&color) && valid_types.contains(&_type) {
-        return Ok(());
+        Ok(())
     }
     else if !valid_colors.contains(&color) {
-        return Err(error::Error::PixelaError("Wrong color name".to_string()))
+        Err(error::Error::InvalidInput("Wrong color name".to_string()))
     }
     else if !valid_types.contains(&_type) {
-        return Err(error::Error::PixelaError("Wrong type".to_string()))
+        Err(error::Error::InvalidInput("Wrong type".to_string()))
     }
     else {
-        return Err(error::Error::PixelaError("Wrong color name and type".to_string()))
+        Err(error::Error::InvalidInput("Wrong color name and type".to_string()))
+    }
+}
+pub fn validate_username(username: &str) -> Result<()> {
+    // pixela's rule for usernames: [a-z][a-z0-9-]{1,32}
+    let mut chars = username.chars();
+    let valid = chars.next().is_some_and(|first| first.is_ascii_lowercase())
+        && (2..=33).contains(&username.len())
+        && chars.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+    if !valid {
+        return Err(error::Error::InvalidInput("A username has to start with a lowercase letter followed by 1 to 32 lowercase letters, digits or hyphens.".to_string()));
+    }
+    Ok(())
+}
+
+pub fn validate_token(token: &str) -> Result<()> {
+    // pixela's rule for tokens: 8 to 128 printable ascii characters
+    if !(8..=128).contains(&token.len()) || !token.chars().all(|c| c.is_ascii_graphic() || c == ' ') {
+        return Err(error::Error::InvalidInput("A token has to be 8 to 128 printable ascii characters.".to_string()));
     }
+    Ok(())
 }
 // enums and structs
 
@@ -264,6 +592,60 @@ pub enum CallResult {
     List(Vec<String>),
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Pixel {
+    pub date: NaiveDate,
+    pub quantity: f64,
+    // pixela's optionalData, a json object kept as the string pixela sent
+    #[serde(default)]
+    pub optional_data: Option<String>,
+}
+
+impl Pixel {
+    // the "note" field of the optional data, other fields like --data are left out
+    pub fn note(&self) -> Option<String> {
+        match serde_json::from_str::<Value>(self.optional_data.as_deref()?).ok()? {
+            Value::Object(mut fields) => match fields.remove("note")? {
+                Value::String(note) => Some(note),
+                _ => None,
+            },
+            // optional data that is only a json string is taken as the note
+            Value::String(note) => Some(note),
+            _ => None,
+        }
+    }
+}
+
+// public profile of a user, every field is optional in both directions
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gravatar_icon_email: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    #[serde(rename = "aboutURL", skip_serializing_if = "Option::is_none")]
+    pub about_url: Option<String>,
+    #[serde(rename = "contributeURLs", skip_serializing_if = "Option::is_none")]
+    pub contribute_urls: Option<Vec<String>>,
+    #[serde(rename = "pinnedGraphID", skip_serializing_if = "Option::is_none")]
+    pub pinned_graph_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GraphDefinition {
+    pub id: String,
+    pub name: String,
+    pub unit: String,
+    #[serde(rename = "type")]
+    pub number_type: String,
+    pub color: String,
+}
+
 #[derive(Debug)]
 pub struct Message {
     json_message: serde_json::Value,
@@ -283,11 +665,14 @@ impl Heatmap {
             quantity,
         }
     }
-    pub fn out_heatmap_info(&self) {
-        println!(
-            "Heatmap name: {}\nPixel date: {}\nCommits amount: {}",
-            &self.name, &self.date, &self.quantity
-        );
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn date(&self) -> &str {
+        &self.date
+    }
+    pub fn quantity(&self) -> u32 {
+        self.quantity
     }
 }
 
@@ -296,15 +681,132 @@ impl Message {
         Message { json_message }
     }
 
-    pub fn out_message(&self) {
-        let message = &self.json_message;
-        if message.get("isSuccess").unwrap() == true {
-            println!(
-                "Success! API responded with: {}",
-                message.get("message").unwrap()
-            )
-        } else {
-            println!("API call failed: {}", message.get("message").unwrap())
+    pub fn text(&self) -> String {
+        match self.json_message.get("message") {
+            Some(serde_json::Value::String(message)) => message.clone(),
+            Some(message) => message.to_string(),
+            None => self.json_message.to_string(),
         }
     }
+
+    pub fn is_success(&self) -> bool {
+        self.json_message.get("isSuccess").is_some_and(|success| success == true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(date: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(date, "%Y%m%d").unwrap()
+    }
+
+    fn pixels(days: &[(&str, f64)]) -> Vec<Pixel> {
+        days.iter().map(|(date, quantity)| Pixel { date: day(date), quantity: *quantity, optional_data: None }).collect()
+    }
+
+    #[test]
+    fn streak_counting() {
+        let history = pixels(&[("20240301", 5.0), ("20240303", 1.0), ("20240304", 2.0), ("20240305", 0.0), ("20240306", 3.0), ("20240307", 4.0)]);
+        assert_eq!(count_streak(&history, day("20240307"), &HashSet::new(), |pixel| pixel.quantity != 0.0), 2);
+        assert_eq!(count_streak(&history, day("20240308"), &HashSet::new(), |pixel| pixel.quantity != 0.0), 2);
+        assert_eq!(count_streak(&history, day("20240309"), &HashSet::new(), |pixel| pixel.quantity != 0.0), 0);
+        assert_eq!(count_streak(&history, day("20240304"), &HashSet::new(), |pixel| pixel.quantity != 0.0), 2);
+        assert_eq!(count_streak(&history, day("20240307"), &HashSet::new(), |pixel| pixel.quantity >= 4.0), 1);
+    }
+
+    #[test]
+    fn cadence_streaks() {
+        // 20240304 is a monday, weekends don't break weekday streaks
+        let history = pixels(&[("20240226", 1.0), ("20240227", 1.0), ("20240301", 1.0), ("20240304", 1.0), ("20240305", 1.0), ("20240306", 1.0)]);
+        let any = |pixel: &Pixel| pixel.quantity != 0.0;
+        assert_eq!(cadence_streak(&history, day("20240306"), &Cadence::Weekdays, &HashSet::new(), any), 4);
+        assert_eq!(cadence_streak(&history, day("20240307"), &Cadence::Weekdays, &HashSet::new(), any), 4);
+        assert_eq!(cadence_streak(&history, day("20240306"), &Cadence::Week { times: 3 }, &HashSet::new(), any), 2);
+        assert_eq!(cadence_streak(&history, day("20240306"), &Cadence::Week { times: 4 }, &HashSet::new(), any), 0);
+        assert_eq!(cadence_streak(&history, day("20240311"), &Cadence::Week { times: 3 }, &HashSet::new(), any), 2);
+        assert_eq!(cadence_streak(&history, day("20240318"), &Cadence::Week { times: 3 }, &HashSet::new(), any), 0);
+        assert_eq!(cadence_streak(&history, day("20240306"), &Cadence::Month { times: 2 }, &HashSet::new(), any), 2);
+        assert_eq!(cadence_streak(&history, day("20240306"), &Cadence::Month { times: 4 }, &HashSet::new(), any), 1);
+        assert_eq!(cadence_streak(&history, day("20240306"), &Cadence::Month { times: 5 }, &HashSet::new(), any), 0);
+    }
+
+    #[test]
+    fn rest_days_bridge_streaks() {
+        let history = pixels(&[("20240226", 1.0), ("20240227", 1.0), ("20240301", 1.0), ("20240304", 1.0), ("20240305", 1.0), ("20240306", 1.0)]);
+        let any = |pixel: &Pixel| pixel.quantity != 0.0;
+        let rest: HashSet<NaiveDate> = [day("20240228"), day("20240229"), day("20240302"), day("20240303")].into_iter().collect();
+        assert_eq!(count_streak(&history, day("20240306"), &rest, any), 6);
+        assert_eq!(count_streak(&history, day("20240307"), &rest, any), 6);
+        assert_eq!(cadence_streak(&history, day("20240306"), &Cadence::Weekdays, &rest, any), 6);
+        let rest_week: HashSet<NaiveDate> = [day("20240219")].into_iter().collect();
+        assert_eq!(cadence_streak(&history, day("20240306"), &Cadence::Week { times: 1 }, &rest_week, any), 2);
+        let lower_target: HashSet<NaiveDate> = [day("20240228")].into_iter().collect();
+        assert_eq!(cadence_streak(&history, day("20240306"), &Cadence::Week { times: 4 }, &lower_target, any), 1);
+        assert_eq!(cadence_streak(&history, day("20240311"), &Cadence::Week { times: 4 }, &lower_target, any), 0);
+        // weeks needing no days end at the start of the history instead of walking back forever
+        let covered: HashSet<NaiveDate> = [day("20240219"), day("20240220")].into_iter().collect();
+        assert_eq!(cadence_streak(&history, day("20240306"), &Cadence::Week { times: 2 }, &covered, any), 2);
+        assert_eq!(cadence_streak(&history, day("20240306"), &Cadence::Week { times: 0 }, &HashSet::new(), any), 2);
+    }
+
+    #[test]
+    fn pixel_parsing() {
+        let response = serde_json::json!({"pixels": [{"date": "20240301", "quantity": "5"}, {"date": "20240302", "quantity": "1.5"}]});
+        assert_eq!(parse_pixels(&response).unwrap(), pixels(&[("20240301", 5.0), ("20240302", 1.5)]));
+        assert!(parse_pixels(&serde_json::json!({"pixels": [{"date": "2024", "quantity": "5"}]})).is_err());
+        assert!(parse_pixels(&serde_json::json!({"pixels": null})).unwrap().is_empty());
+    }
+
+    #[test]
+    fn pixel_notes() {
+        let response = serde_json::json!({"pixels": [
+            {"date": "20240301", "quantity": "5", "optionalData": "{\"note\":\"finished the book\"}"},
+            {"date": "20240302", "quantity": "1", "optionalData": "{\"mood\":3}"},
+            {"date": "20240303", "quantity": "2"},
+            {"date": "20240304", "quantity": "1", "optionalData": "{\"mood\":4,\"note\":\"legs\"}"},
+        ]});
+        let notes: Vec<Option<String>> = parse_pixels(&response).unwrap().iter().map(Pixel::note).collect();
+        assert_eq!(notes, vec![Some("finished the book".to_string()), None, None, Some("legs".to_string())]);
+    }
+
+    #[test]
+    fn token_rules() {
+        assert!(validate_token("correct horse").is_ok());
+        assert!(validate_token("short").is_err());
+        assert!(validate_token(&"x".repeat(129)).is_err());
+        assert!(validate_token("zażółć gęślą").is_err());
+        assert!(validate_username("habit-tracker2").is_ok());
+        assert!(validate_username("a").is_err());
+        assert!(validate_username("2fast").is_err());
+        assert!(validate_username("Habits").is_err());
+        assert!(validate_username(&format!("a{}", "b".repeat(33))).is_err());
+    }
+
+    #[test]
+    fn goal_streaks() {
+        // 20240304 is a monday, weekly totals: 3, 6, 3 and 1 so far this week
+        let history = pixels(&[("20240220", 3.0), ("20240226", 2.0), ("20240301", 4.0), ("20240304", 3.0), ("20240311", 1.0)]);
+        assert_eq!(goal_streak(&history, day("20240312"), &GoalPeriod::Week, 3.0, &HashSet::new()), 3);
+        assert_eq!(goal_streak(&history, day("20240312"), &GoalPeriod::Week, 5.0, &HashSet::new()), 0);
+        assert_eq!(goal_streak(&history, day("20240308"), &GoalPeriod::Week, 5.0, &HashSet::new()), 1);
+        // february totals 9, march 4 so far
+        assert_eq!(goal_streak(&history, day("20240312"), &GoalPeriod::Month, 8.0, &HashSet::new()), 1);
+        assert_eq!(goal_streak(&history, day("20240312"), &GoalPeriod::Month, 4.0, &HashSet::new()), 2);
+        // three rest days lower a weekly goal of 7 to 4
+        let rest: HashSet<NaiveDate> = [day("20240305"), day("20240306"), day("20240307")].into_iter().collect();
+        assert_eq!(goal_streak(&history, day("20240311"), &GoalPeriod::Week, 7.0, &HashSet::new()), 0);
+        assert_eq!(goal_streak(&pixels(&[("20240304", 4.0)]), day("20240311"), &GoalPeriod::Week, 7.0, &rest), 1);
+        assert_eq!(goal_streak(&history, day("20240305"), &GoalPeriod::Day, 3.0, &HashSet::new()), 1);
+    }
+
+    #[test]
+    fn goal_periods() {
+        // 20240304 is a monday
+        let history = pixels(&[("20240229", 1.0), ("20240303", 2.0), ("20240304", 3.0), ("20240306", 4.0)]);
+        assert_eq!(period_total(&history, day("20240306"), &GoalPeriod::Day), 4.0);
+        assert_eq!(period_total(&history, day("20240306"), &GoalPeriod::Week), 7.0);
+        assert_eq!(period_total(&history, day("20240306"), &GoalPeriod::Month), 9.0);
+    }
 }
@@ -0,0 +1,309 @@
+/*
+ Interactive dashboard of every graph, built on the same Worker calls as the commands
+ */
+use std::{collections::HashSet, time::Duration};
+
+use chrono::{Days, NaiveDate};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::Line,
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState},
+    DefaultTerminal, Frame,
+};
+
+use crate::args::{PixelArgs, SumArgs};
+use crate::error::Result;
+use crate::output::Output;
+use crate::pixela::{cadence_streak, GraphDefinition, Pixel};
+use crate::user_data::{AliasesStruct, Cadence, CadencesStruct, User};
+use crate::{forget_pixels, Worker};
+
+// days shown in the heatmap strip of every graph
+const HEATMAP_DAYS: u64 = 28;
+
+struct GraphRow {
+    definition: GraphDefinition,
+    aliases: Vec<String>,
+    cadence: Cadence,
+    pixels: Vec<Pixel>,
+}
+
+enum Mode {
+    Browse,
+    // typing a quantity for the selected graph
+    Set(String),
+}
+
+struct Dashboard<'a> {
+    worker: &'a Worker,
+    rows: Vec<GraphRow>,
+    rest_days: HashSet<NaiveDate>,
+    table: TableState,
+    today: NaiveDate,
+    date: NaiveDate,
+    mode: Mode,
+    status: String,
+    sent: usize,
+}
+
+pub async fn run(worker: &Worker) -> Result<Output> {
+    eprintln!("Loading your graphs...");
+    let mut dashboard = Dashboard::load(worker).await?;
+    let mut terminal = ratatui::init();
+    let result = dashboard.run(&mut terminal).await;
+    ratatui::restore();
+    result?;
+    Ok(Output::message(format!("Sent {} pixel(s).", dashboard.sent)))
+}
+
+// polls instead of a blocking read, so the runtime isn't held up between key presses
+async fn next_event() -> Result<Event> {
+    while !event::poll(Duration::ZERO)? {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    Ok(event::read()?)
+}
+
+impl GraphRow {
+    fn quantity_on(&self, date: NaiveDate) -> f64 {
+        self.pixels.iter().find(|pixel| pixel.date == date).map_or(0.0, |pixel| pixel.quantity)
+    }
+
+    fn set(&mut self, date: NaiveDate, quantity: Option<f64>, optional_data: Option<String>) {
+        self.pixels.retain(|pixel| pixel.date != date);
+        if let Some(quantity) = quantity {
+            self.pixels.push(Pixel { date, quantity, optional_data });
+        }
+    }
+
+    fn label(&self) -> String {
+        match self.aliases.is_empty() {
+            true => self.definition.id.clone(),
+            false => format!("{} ({})", self.definition.id, self.aliases.join(", ")),
+        }
+    }
+
+    fn heatmap(&self, end: NaiveDate) -> String {
+        // one character per day, darker for days closer to the best day of the strip
+        let days: Vec<f64> = (0..HEATMAP_DAYS).rev().map(|ago| self.quantity_on(end - Days::new(ago))).collect();
+        let max = days.iter().cloned().fold(0.0, f64::max);
+        days.iter().map(|quantity| match quantity / max {
+            ratio if ratio.is_nan() || ratio <= 0.0 => '·',
+            ratio if ratio <= 0.25 => '░',
+            ratio if ratio <= 0.5 => '▒',
+            ratio if ratio <= 0.75 => '▓',
+            _ => '█',
+        }).collect()
+    }
+}
+
+impl<'a> Dashboard<'a> {
+    async fn load(worker: &'a Worker) -> Result<Dashboard<'a>> {
+        let aliases = AliasesStruct::load()?;
+        let cadences = CadencesStruct::load()?;
+        let rest_days = User::new()?.get_rest_days()?.into_iter().collect();
+        let mut rows = Vec::new();
+        for definition in worker.graph_definitions().await? {
+            let pixels = worker.pixels(&definition.id).await?;
+            rows.push(GraphRow { aliases: aliases.aliases_of(&definition.id), cadence: cadences.get(&definition.id), pixels, definition });
+        }
+        let today = worker.settings().today();
+        let table = TableState::default().with_selected((!rows.is_empty()).then_some(0));
+        let status = format!("Loaded {} graph(s).", rows.len());
+        Ok(Dashboard { worker, rows, rest_days, table, today, date: today, mode: Mode::Browse, status, sent: 0 })
+    }
+
+    async fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = next_event().await? else { continue };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match std::mem::replace(&mut self.mode, Mode::Browse) {
+                Mode::Set(mut input) => match key.code {
+                    KeyCode::Char(c) if c.is_ascii_digit() || c == '.' => {
+                        input.push(c);
+                        self.mode = Mode::Set(input);
+                    }
+                    KeyCode::Backspace => {
+                        input.pop();
+                        self.mode = Mode::Set(input);
+                    }
+                    KeyCode::Enter if !input.is_empty() => self.send(&input).await,
+                    KeyCode::Esc | KeyCode::Enter => {}
+                    _ => self.mode = Mode::Set(input),
+                },
+                Mode::Browse => match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Down | KeyCode::Char('j') => self.select(1),
+                    KeyCode::Up | KeyCode::Char('k') => self.select(-1),
+                    KeyCode::Left | KeyCode::Char('h') => self.date = self.date - Days::new(1),
+                    KeyCode::Right | KeyCode::Char('l') => self.date = (self.date + Days::new(1)).min(self.today),
+                    KeyCode::Char('t') => self.date = self.today,
+                    KeyCode::Char('+') | KeyCode::Char('i') => self.increment().await,
+                    KeyCode::Char('s') => self.mode = Mode::Set(String::new()),
+                    KeyCode::Char('x') | KeyCode::Delete => self.clear().await,
+                    KeyCode::Char('S') => self.sum().await,
+                    KeyCode::Char('r') => self.reload().await,
+                    _ => {}
+                },
+            }
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [header, body, footer] = Layout::vertical([Constraint::Length(1), Constraint::Min(0), Constraint::Length(2)]).areas(frame.area());
+        let day = match (self.today - self.date).num_days() {
+            0 => "today".to_string(),
+            1 => "yesterday".to_string(),
+            days => format!("{days} days ago"),
+        };
+        let title = format!("habitCLI  {} ({day})", self.worker.settings().format_date(self.date));
+        frame.render_widget(Paragraph::new(title).style(Style::default().add_modifier(Modifier::BOLD)), header);
+
+        let rows = self.rows.iter().map(|row| Row::new(vec![
+            Cell::from(row.label()),
+            Cell::from(format!("{} {}", row.quantity_on(self.date), row.definition.unit)),
+            Cell::from(cadence_streak(&row.pixels, self.today, &row.cadence, &self.rest_days, |pixel| pixel.quantity != 0.0).to_string()),
+            Cell::from(row.heatmap(self.date)).style(Style::default().fg(color(&row.definition.color))),
+        ]));
+        let widths = [Constraint::Fill(2), Constraint::Fill(1), Constraint::Length(6), Constraint::Length(HEATMAP_DAYS as u16)];
+        let table = Table::new(rows, widths)
+            .header(Row::new(["GRAPH", "VALUE", "STREAK", "LAST 4 WEEKS"]).style(Style::default().add_modifier(Modifier::BOLD)))
+            .block(Block::default().borders(Borders::TOP))
+            .row_highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(table, body, &mut self.table);
+
+        let help = match &self.mode {
+            Mode::Set(input) => format!("Set to: {input}_   enter send · esc cancel"),
+            Mode::Browse => "↑↓ graph · ←→ date · t today · + increment · s set · x clear · S sum · r reload · q quit".to_string(),
+        };
+        frame.render_widget(Paragraph::new(vec![Line::from(self.status.as_str()), Line::from(help)]), footer);
+    }
+
+    fn select(&mut self, step: isize) {
+        if self.rows.is_empty() {
+            return;
+        }
+        let selected = self.table.selected().unwrap_or(0).saturating_add_signed(step);
+        self.table.select(Some(selected.min(self.rows.len() - 1)));
+    }
+
+    fn selected(&self) -> Option<usize> {
+        self.table.selected().filter(|index| *index < self.rows.len())
+    }
+
+    async fn increment(&mut self) {
+        let Some(index) = self.selected() else { return };
+        let quantity = self.rows[index].quantity_on(self.date) + 1.0;
+        self.send(&quantity.to_string()).await;
+    }
+
+    async fn send(&mut self, quantity: &str) {
+        let Some(index) = self.selected() else { return };
+        let graph = self.rows[index].definition.id.clone();
+        let date = self.date.format("%Y%m%d").to_string();
+        // sending replaces the pixel, its note and data are sent again with it
+        let optional_data = match self.worker.pixel_optional_data(&graph, self.date).await {
+            Ok(optional_data) => optional_data,
+            Err(err) => {
+                self.status = format!("Error: {err}");
+                return;
+            }
+        };
+        self.status = match self.worker.call_send(PixelArgs { graph: &graph, date: Some(&date), quantity, note: None, data: optional_data.as_deref() }).await {
+            Ok(Output::Sent { summed, sum_error, .. }) => {
+                self.sent += 1;
+                self.rows[index].set(self.date, quantity.parse().ok(), optional_data);
+                self.reload_graphs(&summed).await;
+                match sum_error {
+                    Some(err) => format!("Sent {quantity} to {graph}, but updating sum graphs failed. {err}"),
+                    None => format!("Sent {quantity} to {graph}."),
+                }
+            }
+            Ok(_) => String::new(),
+            Err(err) => format!("Error: {err}"),
+        };
+    }
+
+    async fn clear(&mut self) {
+        let Some(index) = self.selected() else { return };
+        let graph = self.rows[index].definition.id.clone();
+        let date = self.date.format("%Y%m%d").to_string();
+        self.status = match self.worker.clear_pixel(&graph, &date).await {
+            Ok(summed) => {
+                self.rows[index].set(self.date, None, None);
+                self.reload_graphs(&summed).await;
+                format!("Cleared {graph}.")
+            }
+            Err(err) => format!("Error: {err}"),
+        };
+    }
+
+    async fn sum(&mut self) {
+        let date = self.date.format("%Y%m%d").to_string();
+        self.status = match self.worker.handle_sum_graph(SumArgs { date: Some(&date), name: None, from: None, to: None, jobs: 1 }).await {
+            Ok(Output::Sums { days }) => {
+                let summed: Vec<String> = days.into_iter().flat_map(|day| day.summed).collect();
+                self.reload_graphs(&summed).await;
+                format!("Summed {}.", summed.join(", "))
+            }
+            Ok(_) => String::new(),
+            Err(err) => format!("Error: {err}"),
+        };
+    }
+
+    async fn reload(&mut self) {
+        let graphs: Vec<String> = self.rows.iter().map(|row| row.definition.id.clone()).collect();
+        forget_pixels(graphs.iter().map(String::as_str));
+        self.reload_graphs(&graphs).await;
+        if !self.status.starts_with("Error") {
+            self.status = "Reloaded from Pixela.".to_string();
+        }
+    }
+
+    async fn reload_graphs(&mut self, graphs: &[String]) {
+        // pixels of graphs written by pixela's side (sum graphs) are fetched again
+        for row in self.rows.iter_mut().filter(|row| graphs.contains(&row.definition.id)) {
+            match self.worker.pixels(&row.definition.id).await {
+                Ok(pixels) => row.pixels = pixels,
+                Err(err) => self.status = format!("Error: {err}"),
+            }
+        }
+    }
+}
+
+fn color(pixela_color: &str) -> Color {
+    match pixela_color {
+        "shibafu" => Color::Green,
+        "momiji" => Color::Red,
+        "sora" => Color::Blue,
+        "ichou" => Color::Yellow,
+        "ajisai" => Color::Magenta,
+        _ => Color::Reset,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn heatmap_strip() {
+        let date = |day: &str| NaiveDate::parse_from_str(day, "%Y%m%d").unwrap();
+        let row = GraphRow {
+            definition: GraphDefinition { id: "rd1".to_string(), name: "Reading".to_string(), unit: "pages".to_string(), number_type: "int".to_string(), color: "sora".to_string() },
+            aliases: vec!["reading".to_string()],
+            cadence: Cadence::Daily,
+            pixels: vec![Pixel { date: date("20240301"), quantity: 40.0, optional_data: None }, Pixel { date: date("20240229"), quantity: 10.0, optional_data: None }],
+        };
+        let strip = row.heatmap(date("20240301"));
+        assert_eq!(strip.chars().count(), HEATMAP_DAYS as usize);
+        assert!(strip.ends_with("░█"));
+        assert!(strip.starts_with("···"));
+        assert_eq!(row.label(), "rd1 (reading)");
+    }
+}
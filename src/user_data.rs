@@ -1,8 +1,11 @@
-use core::panic;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Utc};
+use chrono_tz::Tz;
 use sled::{self};
-use std::{fmt::Display, fs, path::PathBuf};
+use std::{collections::BTreeMap, fmt::{Display, Write}, fs, path::{Path, PathBuf}, sync::OnceLock};
 use crate::error::{Error, Result, SumGraphError, SumGraphErrorKind};
-use serde::{Deserialize, Serialize};
+use crate::output::OutputFormat;
+use crate::pixela::{GraphDefinition, PIXELA_URL};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 pub struct UserData {
     pub token: String,
@@ -18,6 +21,9 @@ pub struct SumGraphStruct {
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct SumGraphsStruct {
+    // recompute dependent sum graphs automatically after every send
+    #[serde(default)]
+    pub auto_sum: bool,
     pub sum_graphs: Vec<SumGraphStruct>
 }
     
@@ -38,26 +44,512 @@ impl SumGraphsStruct {
             }
 
         }
-        Ok(SumGraphsStruct { sum_graphs })
+        Ok(SumGraphsStruct { auto_sum: false, sum_graphs })
+    }
+
+    pub fn dependents_of(&self, graph_id: &str) -> Vec<SumGraphStruct> {
+        // sum graphs are stored with dependencies first, so a single pass picks up transitive ones
+        let mut changed = vec![graph_id.to_string()];
+        let mut dependents = Vec::new();
+        for sum_graph in &self.sum_graphs {
+            if sum_graph.graphs_to_sum.iter().any(|graph| changed.contains(graph)) {
+                changed.push(sum_graph.sum_graph_name.clone());
+                dependents.push(sum_graph.clone());
+            }
+        }
+        dependents
+    }
+
+    // sum graphs to recompute after the graphs were written, in config order so that dependencies are summed first
+    pub fn to_resum(&self, graphs: &[String], always: bool) -> Vec<SumGraphStruct> {
+        if !always && !self.auto_sum {
+            return Vec::new();
+        }
+        self.sum_graphs.iter()
+            .filter(|sum_graph| graphs.iter().any(|graph| self.dependents_of(graph).contains(sum_graph)))
+            .cloned()
+            .collect()
+    }
+
+    pub fn check(&self, graphs: &[GraphDefinition]) -> Vec<String> {
+        // compares the config with graph definitions from pixela, returns every problem found
+        let mut problems = Vec::new();
+        for sum_graph in &self.sum_graphs {
+            let sum_definition = graphs.iter().find(|graph| graph.id == sum_graph.sum_graph_name);
+            if sum_definition.is_none() {
+                problems.push(format!("Sum graph {} does not exist on your account.", sum_graph.sum_graph_name));
+            }
+            for graph_name in &sum_graph.graphs_to_sum {
+                let Some(definition) = graphs.iter().find(|graph| &graph.id == graph_name) else {
+                    problems.push(format!("Graph {} summed by {} does not exist on your account.", graph_name, sum_graph.sum_graph_name));
+                    continue;
+                };
+                let Some(sum_definition) = sum_definition else { continue };
+                if definition.number_type != sum_definition.number_type {
+                    problems.push(format!("Graph {} is of type {} but sum graph {} is of type {}.", graph_name, definition.number_type, sum_graph.sum_graph_name, sum_definition.number_type));
+                }
+                if definition.unit != sum_definition.unit {
+                    problems.push(format!("Graph {} is measured in {} but sum graph {} is measured in {}.", graph_name, definition.unit, sum_graph.sum_graph_name, sum_definition.unit));
+                }
+            }
+        }
+        problems
     }
 
     pub fn save(&self) -> Result<()>{
-        let path = get_path();
-        dbg!(&path);
-        if !path.exists() { fs::create_dir(&path)? }
-        let toml_string = toml::to_string(self).unwrap();
+        let path = config_dir()?;
+        fs::create_dir_all(&path)?;
+        let toml_string = toml::to_string(self)?;
         fs::write(path.join("sum_graph.toml"), toml_string)?;
         Ok(())
     }
     pub fn load() -> Result<Self> {
-        let path = get_path();
-        let new = toml::from_str(&fs::read_to_string(path.join("sum_graph.toml"))?);
-        if let Ok(new) = new {
-            return Ok(new);
-        } else { return Err(Error::MissingEntryInDatabase("Failed loading local Sum Graphs".to_string())); }
-        
+        let path = config_dir()?;
+        toml::from_str(&fs::read_to_string(path.join("sum_graph.toml"))?).map_err(|err| Error::toml("sum_graph.toml", err))
+    }
+}
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum GoalPeriod {
+    Day,
+    Week,
+    Month,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct Goal {
+    pub graph: String,
+    pub target: f64,
+    pub period: GoalPeriod,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct GoalsStruct {
+    #[serde(default)]
+    pub goals: Vec<Goal>,
+}
+
+impl GoalsStruct {
+    pub fn save(&self) -> Result<()>{
+        let path = config_dir()?;
+        fs::create_dir_all(&path)?;
+        let toml_string = toml::to_string(self)?;
+        fs::write(path.join("goals.toml"), toml_string)?;
+        Ok(())
+    }
+    pub fn load() -> Result<Self> {
+        let path = config_dir()?.join("goals.toml");
+        if !path.exists() {
+            return Ok(GoalsStruct::default());
+        }
+        toml::from_str(&fs::read_to_string(path)?).map_err(|err| Error::toml("goals.toml", err))
+    }
+    pub fn get(&self, graph: &str) -> Option<&Goal> {
+        self.goals.iter().find(|goal| goal.graph == graph)
+    }
+    pub fn set(&mut self, goal: Goal) {
+        self.goals.retain(|old| old.graph != goal.graph);
+        self.goals.push(goal);
+    }
+    pub fn remove(&mut self, graph: &str) -> bool {
+        let before = self.goals.len();
+        self.goals.retain(|goal| goal.graph != graph);
+        before != self.goals.len()
+    }
+}
+
+// config.toml, everything that changes how habitcli behaves
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SettingsStruct {
+    // used when --output isn't passed
+    #[serde(default)]
+    pub output: OutputFormat,
+    // IANA name like "Europe/Warsaw" deciding what today is, the system timezone when missing
+    pub timezone: Option<String>,
+    // strftime format of dates in messages and prompts, pixel dates stay yyyymmdd
+    #[serde(default = "default_date_format")]
+    pub date_format: String,
+    #[serde(default = "default_api_url")]
+    pub api_url: String,
+    #[serde(default)]
+    pub retry: RetrySettings,
+    // defaults of create
+    #[serde(default)]
+    pub graph: GraphSettings,
+    // alias -> graph id, see AliasesStruct
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
+    // reminders of the daemon
+    pub notifications: Option<RemindersStruct>,
+}
+
+// requests rejected by Pixela on purpose (non-supporters get some of those) or that never connected are sent again, timed out reads too
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetrySettings {
+    pub attempts: u32,
+    pub delay_ms: u64,
+}
+
+impl Default for RetrySettings {
+    fn default() -> Self {
+        RetrySettings { attempts: 0, delay_ms: 1000 }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct GraphSettings {
+    #[serde(rename = "type")]
+    pub number_type: String,
+    pub color: String,
+}
+
+impl Default for GraphSettings {
+    fn default() -> Self {
+        GraphSettings { number_type: "int".to_string(), color: "shibafu".to_string() }
+    }
+}
+
+pub const GRAPH_COLORS: [&str; 6] = ["shibafu", "momiji", "sora", "ichou", "ajisai", "kuro"];
+
+fn default_date_format() -> String {
+    "%Y-%m-%d".to_string()
+}
+
+fn default_api_url() -> String {
+    PIXELA_URL.to_string()
+}
+
+impl Default for SettingsStruct {
+    fn default() -> Self {
+        SettingsStruct {
+            output: OutputFormat::default(),
+            timezone: None,
+            date_format: default_date_format(),
+            api_url: default_api_url(),
+            retry: RetrySettings::default(),
+            graph: GraphSettings::default(),
+            aliases: BTreeMap::new(),
+            notifications: None,
+        }
+    }
+}
+
+impl SettingsStruct {
+    pub fn load() -> Result<Self> {
+        Self::from_table(config_table()?)
+    }
+
+    fn from_table(table: toml::Table) -> Result<Self> {
+        let settings: SettingsStruct = table.try_into().map_err(|err| Error::toml("config.toml", err))?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    fn validate(&self) -> Result<()> {
+        let invalid = |message: String| Err(Error::InvalidInput(format!("{message} (in config.toml)")));
+        if let Some(timezone) = &self.timezone {
+            if timezone.parse::<Tz>().is_err() {
+                return invalid(format!("Unknown timezone {timezone}, use a name like \"Europe/Warsaw\"."));
+            }
+        }
+        // chrono only notices an invalid format while writing it
+        if self.date_format.is_empty() || write!(String::new(), "{}", NaiveDate::MIN.format(&self.date_format)).is_err() {
+            return invalid(format!("Invalid date format {:?}, use strftime like \"%d.%m.%Y\".", self.date_format));
+        }
+        if !self.api_url.starts_with("http://") && !self.api_url.starts_with("https://") {
+            return invalid(format!("Invalid api url {}, it has to start with http:// or https://.", self.api_url));
+        }
+        if self.graph.number_type != "int" && self.graph.number_type != "float" {
+            return invalid(format!("Invalid graph type {}, use int or float.", self.graph.number_type));
+        }
+        if !GRAPH_COLORS.contains(&self.graph.color.as_str()) {
+            return invalid(format!("Invalid graph color {}, use one of {}.", self.graph.color, GRAPH_COLORS.join(", ")));
+        }
+        Ok(())
+    }
+
+    // changes one setting by its dotted key and writes config.toml if the result is valid
+    pub fn set(key: &str, value: &str) -> Result<()> {
+        let table = config_table()?;
+        // numbers, booleans and arrays are taken as toml, anything else as text
+        let text = toml::Value::String(value.to_string());
+        let parsed = toml::from_str::<toml::Table>(&format!("value = {value}")).ok()
+            .and_then(|mut parsed| parsed.remove("value"))
+            .unwrap_or_else(|| text.clone());
+        let with_parsed = with_setting(table.clone(), key, parsed)?;
+        let table = match Self::from_table(with_parsed.clone()) {
+            Ok(_) => with_parsed,
+            Err(err) => {
+                let with_text = with_setting(table, key, text)?;
+                Self::from_table(with_text.clone()).map_err(|_| err)?;
+                with_text
+            }
+        };
+        save_config_table(&table)
+    }
+
+    // every setting as dotted key and value, defaults included
+    // top level keys of config.toml, read from the fields so a new setting is known right away
+    pub fn keys() -> Vec<String> {
+        match serde_json::to_value(SettingsStruct::default()) {
+            Ok(serde_json::Value::Object(fields)) => fields.keys().cloned().collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn entries(&self) -> Result<Vec<(String, String)>> {
+        let mut entries = Vec::new();
+        flatten_setting(&mut entries, "", &toml::Value::try_from(self)?);
+        Ok(entries)
+    }
+
+    pub fn timezone(&self) -> Option<Tz> {
+        self.timezone.as_deref().and_then(|timezone| timezone.parse().ok())
+    }
+    // wall clock time in the configured timezone
+    pub fn local(&self, time: DateTime<Utc>) -> NaiveDateTime {
+        match self.timezone() {
+            Some(timezone) => time.with_timezone(&timezone).naive_local(),
+            None => time.with_timezone(&chrono::Local).naive_local(),
+        }
+    }
+    pub fn now(&self) -> NaiveDateTime {
+        self.local(Utc::now())
+    }
+    pub fn today(&self) -> NaiveDate {
+        self.now().date()
+    }
+    pub fn format_date(&self, date: NaiveDate) -> String {
+        date.format(&self.date_format).to_string()
+    }
+    pub fn format_time(&self, time: NaiveDateTime) -> String {
+        time.format(&format!("{} %H:%M", self.date_format)).to_string()
+    }
+}
+
+pub fn config_path() -> Result<PathBuf> {
+    Ok(config_dir()?.join("config.toml"))
+}
+
+fn config_table() -> Result<toml::Table> {
+    let path = config_path()?;
+    if !path.exists() {
+        return Ok(toml::Table::new());
+    }
+    toml::from_str(&fs::read_to_string(path)?).map_err(|err| Error::toml("config.toml", err))
+}
+
+fn save_config_table(table: &toml::Table) -> Result<()> {
+    let path = config_dir()?;
+    fs::create_dir_all(&path)?;
+    fs::write(config_path()?, toml::to_string(table)?)?;
+    Ok(())
+}
+
+fn with_setting(mut table: toml::Table, key: &str, value: toml::Value) -> Result<toml::Table> {
+    let mut parts: Vec<&str> = key.split('.').collect();
+    if parts.iter().any(|part| part.is_empty()) {
+        return Err(Error::InvalidInput(format!("Invalid setting {key}, use a name like \"timezone\" or \"retry.attempts\".")));
+    }
+    let last = parts.pop().expect("Split always gives a part");
+    let mut current = &mut table;
+    for part in parts {
+        current = match current.entry(part).or_insert_with(|| toml::Value::Table(toml::Table::new())) {
+            toml::Value::Table(inner) => inner,
+            _ => return Err(Error::InvalidInput(format!("{part} is a single value, it has no {key}."))),
+        };
+    }
+    current.insert(last.to_string(), value);
+    Ok(table)
+}
+
+fn flatten_setting(entries: &mut Vec<(String, String)>, key: &str, value: &toml::Value) {
+    match value {
+        toml::Value::Table(table) => {
+            for (name, value) in table {
+                let key = if key.is_empty() { name.clone() } else { format!("{key}.{name}") };
+                flatten_setting(entries, &key, value);
+            }
+        }
+        toml::Value::String(text) => entries.push((key.to_string(), text.clone())),
+        value => entries.push((key.to_string(), value.to_string())),
+    }
+}
+
+// local names for graph ids, alias -> graph id
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct AliasesStruct {
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
+}
+
+impl AliasesStruct {
+    pub fn save(&self) -> Result<()>{
+        // aliases live in config.toml
+        let mut table = config_table()?;
+        if self.aliases.is_empty() {
+            table.remove("aliases");
+        } else {
+            table.insert("aliases".to_string(), toml::Value::try_from(&self.aliases)?);
+        }
+        save_config_table(&table)
+    }
+    pub fn load() -> Result<Self> {
+        Ok(AliasesStruct { aliases: SettingsStruct::load()?.aliases })
+    }
+    pub fn resolve<'a>(&'a self, graph: &'a str) -> &'a str {
+        // anything that isn't an alias is taken as a graph id
+        self.aliases.get(graph).map_or(graph, String::as_str)
+    }
+    pub fn aliases_of(&self, graph: &str) -> Vec<String> {
+        self.aliases.iter().filter(|(_, id)| *id == graph).map(|(alias, _)| alias.clone()).collect()
+    }
+}
+
+// graphs asked about by checkin, in this order
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct CheckinStruct {
+    // every graph on the account when empty
+    #[serde(default)]
+    pub graphs: Vec<String>,
+}
+
+impl CheckinStruct {
+    pub fn load() -> Result<Self> {
+        let path = config_dir()?.join("checkin.toml");
+        if !path.exists() {
+            return Ok(CheckinStruct::default());
+        }
+        toml::from_str(&fs::read_to_string(path)?).map_err(|err| Error::toml("checkin.toml", err))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(tag = "every", rename_all = "lowercase")]
+pub enum Cadence {
+    Daily,
+    Weekdays,
+    Week { times: u32 },
+    Month { times: u32 },
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct GraphCadence {
+    pub graph: String,
+    #[serde(flatten)]
+    pub cadence: Cadence,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct CadencesStruct {
+    #[serde(default)]
+    pub cadences: Vec<GraphCadence>,
+}
+
+impl CadencesStruct {
+    pub fn save(&self) -> Result<()>{
+        let path = config_dir()?;
+        fs::create_dir_all(&path)?;
+        let toml_string = toml::to_string(self)?;
+        fs::write(path.join("cadences.toml"), toml_string)?;
+        Ok(())
+    }
+    pub fn load() -> Result<Self> {
+        let path = config_dir()?.join("cadences.toml");
+        if !path.exists() {
+            return Ok(CadencesStruct::default());
+        }
+        toml::from_str(&fs::read_to_string(path)?).map_err(|err| Error::toml("cadences.toml", err))
+    }
+    pub fn get(&self, graph: &str) -> Cadence {
+        // graphs without a configured cadence are daily habits
+        self.cadences.iter().find(|cadence| cadence.graph == graph).map_or(Cadence::Daily, |cadence| cadence.cadence.clone())
+    }
+    pub fn set(&mut self, graph: &str, cadence: Cadence) {
+        self.cadences.retain(|old| old.graph != graph);
+        self.cadences.push(GraphCadence { graph: graph.to_string(), cadence });
+    }
+    pub fn remove(&mut self, graph: &str) -> bool {
+        let before = self.cadences.len();
+        self.cadences.retain(|cadence| cadence.graph != graph);
+        before != self.cadences.len()
+    }
+}
+
+impl Display for Cadence {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Cadence::Daily => write!(f, "every day"),
+            Cadence::Weekdays => write!(f, "every weekday"),
+            Cadence::Week { times } => write!(f, "{times} time(s) per week"),
+            Cadence::Month { times } => write!(f, "{times} time(s) per month"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(rename_all = "lowercase")]
+pub enum ReminderMethod {
+    Desktop,
+    Bell,
+    Hook,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct RemindersStruct {
+    // "HH:MM" in local time
+    pub times: Vec<String>,
+    // graphs to check, every graph on the account when empty
+    #[serde(default)]
+    pub graphs: Vec<String>,
+    #[serde(default = "default_reminder_methods")]
+    pub methods: Vec<ReminderMethod>,
+    // shell command ran with the missing graphs in HABITCLI_MISSING_GRAPHS
+    pub hook: Option<String>,
+}
+
+fn default_reminder_methods() -> Vec<ReminderMethod> {
+    vec![ReminderMethod::Desktop]
+}
+
+impl RemindersStruct {
+    pub fn load() -> Result<Self> {
+        let path = config_path()?;
+        SettingsStruct::load()?.notifications
+            .ok_or_else(|| Error::MissingEntryInDatabase(format!("Reminders are not set up, add a [notifications] section to {}.", path.display())))
+    }
+
+    pub fn next_after(&self, now: NaiveDateTime) -> Result<NaiveDateTime> {
+        // closest configured time after now, wrapping to tomorrow
+        let mut times = Vec::new();
+        for time in &self.times {
+            match NaiveTime::parse_from_str(time, "%H:%M") {
+                Ok(time) => times.push(time),
+                Err(_) => return Err(Error::InvalidInput(format!("Invalid reminder time {time}, use \"HH:MM\"."))),
+            }
+        }
+        let today = now.date();
+        let tomorrow = today + chrono::Days::new(1);
+        times.iter()
+            .map(|time| if today.and_time(*time) > now { today.and_time(*time) } else { tomorrow.and_time(*time) })
+            .min()
+            .ok_or_else(|| Error::MissingEntryInDatabase("No reminder times configured.".to_string()))
     }
 }
+
+impl Display for GoalPeriod {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GoalPeriod::Day => write!(f, "day"),
+            GoalPeriod::Week => write!(f, "week"),
+            GoalPeriod::Month => write!(f, "month"),
+        }
+    }
+}
+
 impl SumGraphStruct {
     pub fn new (sum_graph_name: String, graphs: Vec<String>) -> SumGraphStruct {
         SumGraphStruct { sum_graph_name, graphs_to_sum: graphs }
@@ -66,51 +558,285 @@ impl SumGraphStruct {
 
 
 
+pub const GRAPHS_KEY: &str = "graphs";
+// graphs change rarely, pixels change with every send from another device
+pub const GRAPHS_TTL: TimeDelta = TimeDelta::hours(24);
+pub const PIXELS_TTL: TimeDelta = TimeDelta::minutes(15);
+
+pub fn pixels_key(graph: &str) -> String {
+    format!("pixels/{graph}")
+}
+
+// a single pixel read by get, forgotten together with the pixel list of its graph
+pub fn pixel_key(graph: &str, date: NaiveDate) -> String {
+    format!("{}/{}", pixels_key(graph), date.format("%Y%m%d"))
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Cached<T> {
+    pub fetched_at: DateTime<Utc>,
+    pub value: T,
+}
+
+impl<T> Cached<T> {
+    pub fn age(&self) -> TimeDelta {
+        Utc::now() - self.fetched_at
+    }
+
+    pub fn is_fresh(&self, ttl: TimeDelta) -> bool {
+        self.age() < ttl
+    }
+}
+
+// a running timer, started_at is kept in utc so a timezone change doesn't skew it
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Timer {
+    pub graph: String,
+    pub started_at: DateTime<Utc>,
+}
+
 pub struct User {
     database: sled::Db,
 }
 impl User {
-    pub fn new() -> User {
-        User {
-            database: sled::open(get_path()).expect("Path should be correct"),
+    pub fn new() -> Result<User> {
+        Ok(User {
+            database: sled::open(data_dir()?)?,
+        })
+    }
+}
+
+// every config file habitcli writes or reads
+const CONFIG_FILES: [&str; 5] = ["config.toml", "sum_graph.toml", "goals.toml", "checkin.toml", "cadences.toml"];
+
+// config files that exist in the config directory, by name
+pub fn config_files() -> Result<Vec<String>> {
+    let path = config_dir()?;
+    Ok(CONFIG_FILES.iter().filter(|file| path.join(file).exists()).map(|file| file.to_string()).collect())
+}
+
+// what sled keeps in its directory
+fn database_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if ["conf", "db", "blobs"].contains(&name.as_str()) || name.starts_with("snap.") {
+            files.push(entry.path());
         }
     }
+    Ok(files)
+}
+
+pub fn local_data_exists() -> Result<bool> {
+    Ok(!config_files()?.is_empty() || !database_files(&data_dir()?)?.is_empty())
 }
 
-fn get_path() ->  PathBuf {
-    let path = match directories::BaseDirs::new() {
-        Some(path) => path,
-        None => panic!("Unsupported OS"),
-    };
-    path.config_dir().join("habitCLI")
+pub fn remove_local_data() -> Result<()> {
+    remove_owned(&config_dir()?, &data_dir()?)
+}
+
+// only removes what habitcli created, the directories go too when nothing else is left in them
+fn remove_owned(config: &Path, data: &Path) -> Result<()> {
+    for path in database_files(data)? {
+        if path.is_dir() {
+            fs::remove_dir_all(path)?;
+        } else {
+            fs::remove_file(path)?;
+        }
+    }
+    for file in CONFIG_FILES {
+        let path = config.join(file);
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+    }
+    let mut dirs = vec![data.to_path_buf()];
+    if data.ends_with("database") {
+        dirs.extend(data.parent().map(Path::to_path_buf));
+    }
+    dirs.push(config.to_path_buf());
+    for dir in dirs {
+        // fails on directories that aren't empty, they are kept
+        let _ = fs::remove_dir(dir);
+    }
+    Ok(())
+}
+
+// set from --config-dir, wins over HABITCLI_HOME
+static HOME: OnceLock<PathBuf> = OnceLock::new();
+
+pub fn set_home(path: PathBuf) {
+    let _ = HOME.set(path);
+}
+
+// one directory holding everything, used instead of the platform directories when set
+fn home() -> Option<PathBuf> {
+    HOME.get().cloned().or_else(|| std::env::var_os("HABITCLI_HOME").filter(|home| !home.is_empty()).map(PathBuf::from))
+}
+
+fn base_dirs() -> Result<directories::BaseDirs> {
+    directories::BaseDirs::new().ok_or_else(|| Error::MissingEntryInDatabase("Couldn't find your home directory, set HABITCLI_HOME or pass --config-dir.".to_string()))
+}
+
+// toml config files, $XDG_CONFIG_HOME/habitCLI on linux
+pub fn config_dir() -> Result<PathBuf> {
+    match home() {
+        Some(home) => Ok(home),
+        None => Ok(base_dirs()?.config_dir().join("habitCLI")),
+    }
+}
+
+// the database with credentials, rest days, the timer and the cache, $XDG_DATA_HOME/habitCLI/database on linux
+pub fn data_dir() -> Result<PathBuf> {
+    if let Some(home) = home() {
+        return Ok(home.join("database"));
+    }
+    let base_dirs = base_dirs()?;
+    // older versions kept the database next to the config files, it isn't moved
+    let legacy = base_dirs.config_dir().join("habitCLI");
+    if legacy.join("conf").exists() && legacy.join("db").exists() {
+        return Ok(legacy);
+    }
+    Ok(base_dirs.data_dir().join("habitCLI").join("database"))
 }
 
 
 impl User {
     pub fn set_user_data(&self, name: &str, token: &str) -> Result<()> {
-        // puts user specific data in the local database
-        let _ = self.database.insert("token", token)?;
-        let _ = self.database.insert("name", name)?;
+        // puts user specific data in the local database, name and token are written together or not at all
+        let mut batch = sled::Batch::default();
+        batch.insert("token", token);
+        batch.insert("name", name);
+        self.database.apply_batch(batch)?;
+        self.database.flush()?;
+        Ok(())
+    }
+
+    pub fn remove_user_data(&self) -> Result<()> {
+        let mut batch = sled::Batch::default();
+        batch.remove("token");
+        batch.remove("name");
+        self.database.apply_batch(batch)?;
+        self.database.flush()?;
         Ok(())
     }
 
     pub fn get_user_data(&self) -> Result<UserData> {
         // gets all user local data from database and pass it in a standarized way
-        let none_message = "Log in first. (habitcli login -h)";
+        let none_message = "You are not logged in.";
         let token = if let Some(token_vector) = self.database.get("token")? {
             std::str::from_utf8(&token_vector).unwrap().to_string()
         } else {
-            return Err(Error::from(none_message));
+            return Err(Error::AuthError(none_message.to_string()));
         };
 
         let name = if let Some(name_vector) = self.database.get("name")? {
             std::str::from_utf8(&name_vector).unwrap().to_string()
         } else {
-            return Err(Error::from(none_message));
+            return Err(Error::AuthError(none_message.to_string()));
         };
 
         Ok(UserData { token, name, sum_graphs: None})
     }
+
+    // pixela responses are cached in their own tree, a login as someone else starts it over
+    pub fn get_cached<T: DeserializeOwned>(&self, key: &str) -> Result<Option<Cached<T>>> {
+        let cache = self.database.open_tree("cache")?;
+        match cache.get(key)? {
+            Some(cached) => Ok(serde_json::from_slice(&cached).ok()),
+            None => Ok(None),
+        }
+    }
+
+    pub fn set_cached<T: Serialize>(&self, key: &str, value: &T) -> Result<()> {
+        let cache = self.database.open_tree("cache")?;
+        let cached = Cached { fetched_at: Utc::now(), value };
+        cache.insert(key, serde_json::to_vec(&cached)?)?;
+        Ok(())
+    }
+
+    // removes the keys together with the ones under them, like single pixels under a pixel list
+    pub fn remove_cached(&self, keys: &[String]) -> Result<()> {
+        let cache = self.database.open_tree("cache")?;
+        for key in keys {
+            cache.remove(key)?;
+            for entry in cache.scan_prefix(format!("{key}/")) {
+                cache.remove(entry?.0)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn cache_len(&self) -> Result<usize> {
+        Ok(self.database.open_tree("cache")?.len())
+    }
+
+    pub fn clear_cache(&self) -> Result<()> {
+        self.database.open_tree("cache")?.clear()?;
+        Ok(())
+    }
+
+    // graph ids of cached graph definitions, shell completion never waits for pixela
+    pub fn get_cached_graph_list(&self) -> Result<Vec<String>> {
+        let graphs = self.get_cached::<Vec<GraphDefinition>>(GRAPHS_KEY)?;
+        Ok(graphs.map(|graphs| graphs.value.into_iter().map(|graph| graph.id).collect()).unwrap_or_default())
+    }
+
+    // at most one timer runs at a time, it lives in its own tree
+    pub fn set_timer(&self, timer: &Timer) -> Result<()> {
+        self.database.open_tree("timer")?.insert("running", serde_json::to_vec(timer)?)?;
+        Ok(())
+    }
+
+    pub fn get_timer(&self) -> Result<Option<Timer>> {
+        match self.database.open_tree("timer")?.get("running")? {
+            Some(timer) => Ok(Some(serde_json::from_slice(&timer)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn remove_timer(&self) -> Result<()> {
+        self.database.open_tree("timer")?.remove("running")?;
+        Ok(())
+    }
+
+    // rest days are kept in their own tree, keyed by "yyyymmdd" so they stay sorted
+    pub fn add_rest_days(&self, days: &[NaiveDate]) -> Result<()> {
+        let rest_days = self.database.open_tree("rest_days")?;
+        for day in days {
+            rest_days.insert(day.format("%Y%m%d").to_string(), "")?;
+        }
+        Ok(())
+    }
+
+    pub fn remove_rest_days(&self, days: &[NaiveDate]) -> Result<usize> {
+        let rest_days = self.database.open_tree("rest_days")?;
+        let mut removed = 0;
+        for day in days {
+            if rest_days.remove(day.format("%Y%m%d").to_string())?.is_some() {
+                removed += 1;
+            }
+        }
+        Ok(removed)
+    }
+
+    pub fn get_rest_days(&self) -> Result<Vec<NaiveDate>> {
+        let rest_days = self.database.open_tree("rest_days")?;
+        let mut days = Vec::new();
+        for entry in rest_days.iter() {
+            let (key, _) = entry?;
+            let day = std::str::from_utf8(&key).ok().and_then(|key| NaiveDate::parse_from_str(key, "%Y%m%d").ok());
+            match day {
+                Some(day) => days.push(day),
+                None => return Err(Error::MissingEntryInDatabase("Corrupted rest day in the local database".to_string())),
+            }
+        }
+        Ok(days)
+    }
 }
 impl Display for SumGraphStruct {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -140,7 +866,7 @@ mod tests {
     use super::*;
 #[test]
     fn test_getdata() {
-        let user = User::new();
+        let user = User::new().unwrap();
         let name = user.database.get("name").unwrap().unwrap();
         let name = std::str::from_utf8(&name);
         let token = user.database.get("token").unwrap().unwrap();
@@ -159,13 +885,138 @@ mod tests {
         let sumgraph_B = SumGraphStruct::new("B".to_string(), vec!["g","x","z"].iter().map(|s| s.to_string()).collect());
         let sumgraph_C = SumGraphStruct::new("C".to_string(), vec!["A","g","z"].iter().map(|s| s.to_string()).collect());
         let sum_graphs = vec![sumgraph_A.clone(), sumgraph_B.clone(), sumgraph_C.clone()];
-        assert_eq!(SumGraphsStruct::build(sum_graphs).unwrap(), SumGraphsStruct{sum_graphs: vec![sumgraph_B, sumgraph_A, sumgraph_C]});
+        assert_eq!(SumGraphsStruct::build(sum_graphs).unwrap(), SumGraphsStruct{auto_sum: false, sum_graphs: vec![sumgraph_B, sumgraph_A, sumgraph_C]});
 
         let sumgraph_A = SumGraphStruct::new("A".to_string(), vec!["B","x","z"].iter().map(|s| s.to_string()).collect());
         let sumgraph_B = SumGraphStruct::new("B".to_string(), vec!["C","x","z"].iter().map(|s| s.to_string()).collect());
         let sumgraph_C = SumGraphStruct::new("C".to_string(), vec!["A","g","z"].iter().map(|s| s.to_string()).collect());
         let sum_graphs = vec![sumgraph_A.clone(), sumgraph_B.clone(), sumgraph_C.clone()];
-        assert_eq!(SumGraphsStruct::build(sum_graphs).unwrap(), SumGraphsStruct{sum_graphs: vec![sumgraph_C, sumgraph_B, sumgraph_A]});
+        assert_eq!(SumGraphsStruct::build(sum_graphs).unwrap(), SumGraphsStruct{auto_sum: false, sum_graphs: vec![sumgraph_C, sumgraph_B, sumgraph_A]});
+    }
+
+#[test]
+    fn sum_dependents() {
+        let sumgraph_A = SumGraphStruct::new("A".to_string(), vec!["x","y"].iter().map(|s| s.to_string()).collect());
+        let sumgraph_B = SumGraphStruct::new("B".to_string(), vec!["A","z"].iter().map(|s| s.to_string()).collect());
+        let sumgraph_C = SumGraphStruct::new("C".to_string(), vec!["z","g"].iter().map(|s| s.to_string()).collect());
+        let sum_graphs = SumGraphsStruct::build(vec![sumgraph_A.clone(), sumgraph_B.clone(), sumgraph_C.clone()]).unwrap();
+        assert_eq!(sum_graphs.dependents_of("x"), vec![sumgraph_A, sumgraph_B.clone()]);
+        assert_eq!(sum_graphs.dependents_of("z"), vec![sumgraph_B, sumgraph_C]);
+        assert!(sum_graphs.dependents_of("q").is_empty());
+    }
+
+#[test]
+    fn resum_without_auto_sum() {
+        let sum_graphs = SumGraphsStruct::build(vec![
+            SumGraphStruct::new("total".to_string(), vec!["week".to_string(), "c".to_string()]),
+            SumGraphStruct::new("week".to_string(), vec!["a".to_string(), "b".to_string()]),
+        ]).unwrap();
+        assert!(!sum_graphs.auto_sum);
+        assert!(sum_graphs.to_resum(&["a".to_string()], false).is_empty());
+        let names: Vec<String> = sum_graphs.to_resum(&["a".to_string()], true).into_iter().map(|sum_graph| sum_graph.sum_graph_name).collect();
+        assert_eq!(names, vec!["week", "total"]);
+        assert!(sum_graphs.to_resum(&["x".to_string()], true).is_empty());
+    }
+
+#[test]
+    fn sum_check() {
+        let definition = |id: &str, number_type: &str, unit: &str| GraphDefinition {
+            id: id.to_string(), name: id.to_string(), unit: unit.to_string(), number_type: number_type.to_string(), color: "shibafu".to_string(),
+        };
+        let graphs = vec![definition("sum", "int", "minutes"), definition("a", "int", "minutes"), definition("b", "float", "hours")];
+        let sum_graphs = SumGraphsStruct::build(vec![SumGraphStruct::new("sum".to_string(), vec!["a".to_string(), "b".to_string(), "c".to_string()])]).unwrap();
+        let problems = sum_graphs.check(&graphs);
+        assert_eq!(problems.len(), 3);
+        assert!(problems[0].contains("type float"));
+        assert!(problems[1].contains("hours"));
+        assert!(problems[2].contains("Graph c"));
+
+        let missing = SumGraphsStruct::build(vec![SumGraphStruct::new("gone".to_string(), vec!["a".to_string()])]).unwrap();
+        assert_eq!(missing.check(&graphs).len(), 1);
+    }
+
+#[test]
+    fn aliases_toml() {
+        let aliases: AliasesStruct = toml::from_str("[aliases]\nreading = \"rd1\"\nbooks = \"rd1\"\ngym = \"gymw\"").unwrap();
+        assert_eq!(aliases.resolve("reading"), "rd1");
+        assert_eq!(aliases.resolve("rd1"), "rd1");
+        assert_eq!(aliases.aliases_of("rd1"), vec!["books", "reading"]);
+        assert!(aliases.aliases_of("unknown").is_empty());
+    }
+
+#[test]
+    fn settings_toml() {
+        let settings = SettingsStruct::from_table(toml::Table::new()).unwrap();
+        assert_eq!(settings, SettingsStruct::default());
+        assert!(settings.entries().unwrap().contains(&("retry.delay_ms".to_string(), "1000".to_string())));
+        // settings that are unset by default are still known
+        assert!(["timezone", "notifications", "aliases", "graph"].iter().all(|key| SettingsStruct::keys().contains(&key.to_string())));
+
+        let table: toml::Table = toml::from_str("output = \"json\"\ntimezone = \"Asia/Tokyo\"\n[graph]\ntype = \"float\"\n[aliases]\nreading = \"rd1\"").unwrap();
+        let settings = SettingsStruct::from_table(table).unwrap();
+        assert_eq!(settings.output, OutputFormat::Json);
+        assert_eq!(settings.graph, GraphSettings { number_type: "float".to_string(), color: "shibafu".to_string() });
+        let midnight = "2024-03-01T15:30:00Z".parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(settings.local(midnight).date(), NaiveDate::from_ymd_opt(2024, 3, 2).unwrap());
+        assert!(settings.entries().unwrap().contains(&("aliases.reading".to_string(), "rd1".to_string())));
+
+        for invalid in ["timezone = \"Mars/Base\"", "date_format = \"%Q\"", "[graph]\ncolor = \"red\"", "colour = \"sora\""] {
+            assert!(SettingsStruct::from_table(toml::from_str(invalid).unwrap()).is_err(), "{invalid}");
+        }
+
+        let table = with_setting(toml::Table::new(), "retry.attempts", toml::Value::Integer(3)).unwrap();
+        assert_eq!(SettingsStruct::from_table(table.clone()).unwrap().retry.attempts, 3);
+        assert!(with_setting(table, "retry.attempts.more", toml::Value::Integer(1)).is_err());
+    }
+
+#[test]
+    fn cache_freshness() {
+        let cached = Cached { fetched_at: Utc::now() - TimeDelta::minutes(20), value: vec!["rd1".to_string()] };
+        assert!(!cached.is_fresh(PIXELS_TTL));
+        assert!(cached.is_fresh(GRAPHS_TTL));
+        let json = serde_json::to_vec(&cached).unwrap();
+        assert_eq!(serde_json::from_slice::<Cached<Vec<String>>>(&json).unwrap().value, cached.value);
+    }
+
+#[test]
+    fn cadences_toml() {
+        let mut cadences = CadencesStruct::default();
+        cadences.set("gymw", Cadence::Week { times: 3 });
+        cadences.set("rd1", Cadence::Weekdays);
+        let toml_string = toml::to_string(&cadences).unwrap();
+        assert!(toml_string.contains("every = \"week\""));
+        assert_eq!(toml::from_str::<CadencesStruct>(&toml_string).unwrap(), cadences);
+        assert_eq!(cadences.get("other"), Cadence::Daily);
+    }
+
+#[test]
+    fn reminder_times() {
+        let reminders = RemindersStruct { times: vec!["20:00".to_string(), "08:30".to_string()], graphs: vec![], methods: default_reminder_methods(), hook: None };
+        let at = |date: &str| NaiveDateTime::parse_from_str(date, "%Y%m%d %H:%M").unwrap();
+        assert_eq!(reminders.next_after(at("20240301 07:00")).unwrap(), at("20240301 08:30"));
+        assert_eq!(reminders.next_after(at("20240301 08:30")).unwrap(), at("20240301 20:00"));
+        assert_eq!(reminders.next_after(at("20240301 21:00")).unwrap(), at("20240302 08:30"));
+
+        let invalid = RemindersStruct { times: vec!["8pm".to_string()], graphs: vec![], methods: vec![], hook: None };
+        assert!(invalid.next_after(at("20240301 07:00")).is_err());
+    }
+
+#[test]
+    fn reset_keeps_foreign_files() {
+        let home = std::env::temp_dir().join(format!("habitcli-reset-{}", std::process::id()));
+        let data = home.join("database");
+        fs::create_dir_all(data.join("blobs")).unwrap();
+        for file in ["config.toml", "goals.toml", "notes.txt", "database/conf", "database/db", "database/snap.0001"] {
+            fs::write(home.join(file), "").unwrap();
+        }
+        remove_owned(&home, &data).unwrap();
+        assert!(!data.exists());
+        assert!(!home.join("config.toml").exists());
+        assert!(home.join("notes.txt").exists());
+
+        fs::remove_file(home.join("notes.txt")).unwrap();
+        remove_owned(&home, &data).unwrap();
+        assert!(!home.exists());
     }
 
 #[test]
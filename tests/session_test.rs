@@ -0,0 +1,289 @@
+use std::time::Duration;
+
+use chrono::NaiveDate;
+use habitcli::{error::{self, Error}, pixela::{CallResult, Profile, Session}};
+use wiremock::{matchers::{body_json, method, path, query_param}, Mock, MockServer, ResponseTemplate};
+
+// a session pointed at the mock server instead of pixela, with the same /v1 prefix as pixela
+async fn mock_session() -> (MockServer, Session) {
+    let server = MockServer::start().await;
+    let session = Session::new().with_base_url(&format!("{}/v1", server.uri())).with_timeout(Duration::from_millis(500));
+    (server, session)
+}
+
+#[tokio::test]
+async fn pixel_is_read() {
+    let (server, session) = mock_session().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/users/tester/graphs/rd1/20240301"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"quantity": "30"})))
+        .mount(&server)
+        .await;
+
+    let url = session.graph_url("tester", "rd1");
+    match session.get_pixel_info(&url, "rd1", Some("20240301"), "token").await.unwrap() {
+        CallResult::Heatmap(heatmap) => assert_eq!(heatmap.quantity(), 30),
+        _ => panic!("Expected a heatmap"),
+    }
+}
+
+#[tokio::test]
+async fn missing_pixel_is_zero() {
+    let (server, session) = mock_session().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404).set_body_json(serde_json::json!({"message": "Specified pixel not found.", "isSuccess": false})))
+        .mount(&server)
+        .await;
+
+    let url = session.graph_url("tester", "rd1");
+    match session.get_pixel_info(&url, "rd1", Some("20240301"), "token").await.unwrap() {
+        CallResult::Heatmap(heatmap) => assert_eq!(heatmap.quantity(), 0),
+        _ => panic!("Expected a heatmap"),
+    }
+}
+
+#[tokio::test]
+async fn wrong_token_is_an_auth_error() {
+    let (server, session) = mock_session().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(404).set_body_json(serde_json::json!({"message": "User `tester` does not exist or the token is wrong.", "isSuccess": false})))
+        .mount(&server)
+        .await;
+
+    let err = session.get_graph_list("token", &session.graphs_url("tester")).await.err().unwrap();
+    assert!(matches!(err, Error::AuthError(_)));
+    assert_eq!(err.exit_code(), error::EXIT_AUTH);
+}
+
+#[tokio::test]
+async fn connection_refused() {
+    // a port that nothing listens on anymore
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    drop(listener);
+    let session = Session::new().with_base_url(&format!("http://{address}"));
+
+    let err = session.get_graph_list("token", &session.graphs_url("tester")).await.err().unwrap();
+    assert!(matches!(err, Error::ReqwestError(ref err) if err.is_connect()));
+    assert_eq!(err.exit_code(), error::EXIT_NETWORK);
+}
+
+#[tokio::test]
+async fn timeout() {
+    let (server, session) = mock_session().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"graphs": []})).set_delay(Duration::from_secs(5)))
+        .mount(&server)
+        .await;
+
+    let err = session.get_graph_list("token", &session.graphs_url("tester")).await.err().unwrap();
+    assert!(matches!(err, Error::ReqwestError(ref err) if err.is_timeout()));
+    assert_eq!(err.exit_code(), error::EXIT_NETWORK);
+}
+
+#[tokio::test]
+async fn malformed_body() {
+    let (server, session) = mock_session().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(502).set_body_string("<html>Bad Gateway</html>"))
+        .mount(&server)
+        .await;
+
+    let url = session.graph_url("tester", "rd1");
+    let err = session.send_pixel(&url, "5", Some("20240301"), None, "token").await.err().unwrap();
+    assert!(matches!(err, Error::ReqwestError(ref err) if err.is_decode()));
+    assert_eq!(err.exit_code(), error::EXIT_PIXELA);
+}
+
+#[tokio::test]
+async fn malformed_quantity() {
+    let (server, session) = mock_session().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"quantity": 30})))
+        .mount(&server)
+        .await;
+
+    let url = session.graph_url("tester", "rd1");
+    let err = session.get_pixel_info(&url, "rd1", Some("20240301"), "token").await.err().unwrap();
+    assert!(matches!(err, Error::PixelaError(_)));
+}
+
+#[tokio::test]
+async fn clearing_a_missing_pixel() {
+    let (server, session) = mock_session().await;
+    Mock::given(method("DELETE"))
+        .and(path("/v1/users/tester/graphs/rd1/20240301"))
+        .respond_with(ResponseTemplate::new(404).set_body_json(serde_json::json!({"message": "Specified pixel not found.", "isSuccess": false})))
+        .mount(&server)
+        .await;
+    Mock::given(method("DELETE"))
+        .and(path("/v1/users/tester/graphs/rd1/20240302"))
+        .respond_with(ResponseTemplate::new(503).set_body_json(serde_json::json!({"message": "Please retry this request.", "isSuccess": false})))
+        .mount(&server)
+        .await;
+
+    let url = session.graph_url("tester", "rd1");
+    assert!(session.delete_pixel(&url, "20240301", "token").await.is_ok());
+    assert!(matches!(session.delete_pixel(&url, "20240302", "token").await, Err(Error::PixelaError(_))));
+}
+
+#[tokio::test]
+async fn adding_to_todays_pixel() {
+    let (server, session) = mock_session().await;
+    Mock::given(method("PUT"))
+        .and(path("/v1/users/tester/graphs/rd1/add"))
+        .and(body_json(serde_json::json!({"quantity": "25"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"message": "Success.", "isSuccess": true})))
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/v1/users/tester/graphs/gym/add"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(serde_json::json!({"message": "Specified graph not found.", "isSuccess": false})))
+        .mount(&server)
+        .await;
+
+    assert!(session.add_quantity(&session.graph_url("tester", "rd1"), "25", "token").await.is_ok());
+    assert!(matches!(session.add_quantity(&session.graph_url("tester", "gym"), "25", "token").await, Err(Error::PixelaError(_))));
+}
+
+#[tokio::test]
+async fn sending_a_note() {
+    let (server, session) = mock_session().await;
+    Mock::given(method("POST"))
+        .and(path("/v1/users/tester/graphs/rd1"))
+        .and(body_json(serde_json::json!({"date": "20240301", "quantity": "5", "optionalData": "{\"note\":\"chapter 3\"}"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"message": "Success.", "isSuccess": true})))
+        .mount(&server)
+        .await;
+
+    let url = session.graph_url("tester", "rd1");
+    match session.send_pixel(&url, "5", Some("20240301"), Some("{\"note\":\"chapter 3\"}"), "token").await.unwrap() {
+        CallResult::ApiResponse(msg) => assert!(msg.is_success()),
+        _ => panic!("Expected a response"),
+    }
+}
+
+#[tokio::test]
+async fn account_management() {
+    let (server, session) = mock_session().await;
+    Mock::given(method("PUT"))
+        .and(path("/v1/users/tester"))
+        .and(body_json(serde_json::json!({"newToken": "new-token-1"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"message": "Success.", "isSuccess": true})))
+        .mount(&server)
+        .await;
+    Mock::given(method("PUT"))
+        .and(path("/@tester"))
+        .and(body_json(serde_json::json!({"displayName": "Tester", "timezone": "Europe/Warsaw"})))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"message": "Success.", "isSuccess": true})))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1/users/tester"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"displayName": "Tester", "aboutURL": "https://example.com", "isSupporter": false})))
+        .mount(&server)
+        .await;
+
+    session.update_token("tester", "token", "new-token-1").await.unwrap();
+    let profile = Profile { display_name: Some("Tester".to_string()), timezone: Some("Europe/Warsaw".to_string()), ..Profile::default() };
+    session.update_profile("tester", "token", &profile).await.unwrap();
+    let profile = session.get_profile("tester", "token").await.unwrap();
+    assert_eq!(profile.about_url.as_deref(), Some("https://example.com"));
+    assert_eq!(profile.display_name.as_deref(), Some("Tester"));
+}
+
+#[tokio::test]
+async fn telling_a_missing_user_from_a_wrong_token() {
+    let (server, session) = mock_session().await;
+    Mock::given(method("GET"))
+        .and(path("/@tester"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("<html>tester</html>"))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/@nobody"))
+        .respond_with(ResponseTemplate::new(404).set_body_string("<html>Not Found</html>"))
+        .mount(&server)
+        .await;
+
+    assert!(session.user_exists("tester").await.unwrap());
+    assert!(!session.user_exists("nobody").await.unwrap());
+}
+
+#[tokio::test]
+async fn rejected_requests_are_retried() {
+    let (server, session) = mock_session().await;
+    let rejected = serde_json::json!({"message": "Please retry this request.", "isSuccess": false, "isRejected": true});
+    Mock::given(method("GET"))
+        .and(path("/v1/users/tester/graphs/rd1/20240301"))
+        .respond_with(ResponseTemplate::new(503).set_body_json(rejected.clone()))
+        .up_to_n_times(2)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1/users/tester/graphs/rd1/20240301"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"quantity": "30"})))
+        .mount(&server)
+        .await;
+
+    let url = session.graph_url("tester", "rd1");
+    let session = session.with_retries(2, Duration::from_millis(10));
+    match session.get_pixel_info(&url, "rd1", Some("20240301"), "token").await.unwrap() {
+        CallResult::Heatmap(heatmap) => assert_eq!(heatmap.quantity(), 30),
+        _ => panic!("Expected a heatmap"),
+    }
+}
+
+#[tokio::test]
+async fn single_pixel_outside_the_pixel_list() {
+    let (server, session) = mock_session().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/users/tester/graphs/rd1/20200301"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"quantity": "2.5", "optionalData": "{\"note\":\"old\"}"})))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/v1/users/tester/graphs/rd1/20200302"))
+        .respond_with(ResponseTemplate::new(404).set_body_json(serde_json::json!({"message": "Specified pixel not found.", "isSuccess": false})))
+        .mount(&server)
+        .await;
+
+    let date = |date: &str| NaiveDate::parse_from_str(date, "%Y%m%d").unwrap();
+    let pixel = session.get_pixel("tester", "token", "rd1", date("20200301")).await.unwrap().unwrap();
+    assert_eq!(pixel.quantity, 2.5);
+    assert_eq!(pixel.note().as_deref(), Some("old"));
+    assert!(session.get_pixel("tester", "token", "rd1", date("20200302")).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn timed_out_writes_are_not_retried() {
+    let (server, session) = mock_session().await;
+    Mock::given(method("PUT"))
+        .and(path("/v1/users/tester/graphs/rd1/add"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"isSuccess": true})).set_delay(Duration::from_secs(2)))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    // pixela may have added the quantity before the reply got lost, adding it again would count it twice
+    let session = session.with_retries(2, Duration::from_millis(10));
+    let err = session.add_quantity(&session.graph_url("tester", "rd1"), "5", "token").await.err().unwrap();
+    assert!(matches!(err, Error::ReqwestError(ref err) if err.is_timeout()));
+}
+
+#[tokio::test]
+async fn pixels_older_than_a_year() {
+    let (server, session) = mock_session().await;
+    Mock::given(method("GET"))
+        .and(path("/v1/users/tester/graphs/rd1/pixels"))
+        .and(query_param("from", "20190302"))
+        .and(query_param("to", "20200301"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({"pixels": [{"date": "20190810", "quantity": "1", "optionalData": "{\"note\":\"first\"}"}]})))
+        .mount(&server)
+        .await;
+
+    let date = |date: &str| NaiveDate::parse_from_str(date, "%Y%m%d").unwrap();
+    let pixels = session.get_pixels_between("tester", "token", "rd1", date("20190302"), date("20200301")).await.unwrap();
+    assert_eq!(pixels.len(), 1);
+    assert_eq!(pixels[0].note().as_deref(), Some("first"));
+}